@@ -0,0 +1,269 @@
+use std::{fs, io, path::Path};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::models::{CapacityHint, Location, Venue, VenueSource};
+
+/// Search radius used when no attendee set a max distance
+pub const DEFAULT_RADIUS_MILES: f64 = 5.0;
+
+/// What the matching phase is looking for when it asks a provider for venues.
+#[derive(Debug, Clone)]
+pub struct VenueQuery {
+    pub centroid: Location,
+    pub radius_miles: f64,
+    pub category: Option<String>,
+    /// Venues closed at this time are left out
+    pub open_at: Option<DateTime<Utc>>,
+}
+
+/// A source of venues beyond the group's favorites.
+pub trait VenueProvider: Send + Sync {
+    fn search(&self, query: &VenueQuery) -> Vec<Venue>;
+}
+
+/// Map a ping's activity type onto the venue category discovery searches for.
+pub fn category_for_activity(activity_type: &str) -> Option<&'static str> {
+    match activity_type.to_ascii_lowercase().as_str() {
+        "drinks" | "beers" | "cocktails" => Some("bar"),
+        "dinner" | "lunch" | "brunch" => Some("restaurant"),
+        "coffee" => Some("cafe"),
+        _ => None,
+    }
+}
+
+/// Daily opening hours in UTC. `closes` before `opens` means the venue
+/// closes after midnight.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OpeningHours {
+    pub opens: NaiveTime,
+    pub closes: NaiveTime,
+}
+
+impl OpeningHours {
+    pub fn is_open_at(&self, at: DateTime<Utc>) -> bool {
+        let time = at.time();
+        if self.opens <= self.closes {
+            self.opens <= time && time < self.closes
+        } else {
+            time >= self.opens || time < self.closes
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VenueProperties {
+    #[serde(default)]
+    id: Option<Uuid>,
+    name: String,
+    #[serde(default)]
+    area: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    vibe_tags: Vec<String>,
+    #[serde(default)]
+    capacity_hint: Option<CapacityHint>,
+    #[serde(default)]
+    hours: Option<OpeningHours>,
+}
+
+/// Plain JSON fixture entry: venue properties alongside its location
+#[derive(Debug, Deserialize)]
+struct JsonVenue {
+    location: Location,
+    #[serde(flatten)]
+    properties: VenueProperties,
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    /// GeoJSON order: `[lng, lat]`
+    coordinates: [f64; 2],
+}
+
+#[derive(Debug, Deserialize)]
+struct Feature {
+    geometry: Point,
+    properties: VenueProperties,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum VenueFile {
+    GeoJson { features: Vec<Feature> },
+    Json(Vec<JsonVenue>),
+}
+
+#[derive(Debug, Clone)]
+struct ListedVenue {
+    venue: Venue,
+    location: Location,
+    hours: Option<OpeningHours>,
+}
+
+/// Offline provider backed by a local GeoJSON `FeatureCollection` of points,
+/// or a JSON array of venues with a `location`.
+#[derive(Debug, Clone)]
+pub struct FileVenueProvider {
+    venues: Vec<ListedVenue>,
+}
+
+impl FileVenueProvider {
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::from_json(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn from_json(contents: &str) -> serde_json::Result<Self> {
+        let entries: Vec<(Location, VenueProperties)> = match serde_json::from_str(contents)? {
+            VenueFile::GeoJson { features } => features
+                .into_iter()
+                .map(|f| {
+                    let [lng, lat] = f.geometry.coordinates;
+                    (Location { lat, lng }, f.properties)
+                })
+                .collect(),
+            VenueFile::Json(venues) => venues
+                .into_iter()
+                .map(|v| (v.location, v.properties))
+                .collect(),
+        };
+
+        let venues = entries
+            .into_iter()
+            .map(|(location, props)| ListedVenue {
+                venue: Venue {
+                    id: props.id.unwrap_or_else(Uuid::new_v4),
+                    name: props.name,
+                    location: Some(location.clone()),
+                    area: props.area,
+                    category: props.category,
+                    vibe_tags: props.vibe_tags,
+                    capacity_hint: props.capacity_hint,
+                    source: VenueSource::Discovery,
+                },
+                location,
+                hours: props.hours,
+            })
+            .collect();
+
+        Ok(Self { venues })
+    }
+
+    pub fn len(&self) -> usize {
+        self.venues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.venues.is_empty()
+    }
+}
+
+impl VenueProvider for FileVenueProvider {
+    fn search(&self, query: &VenueQuery) -> Vec<Venue> {
+        self.venues
+            .iter()
+            .filter(|v| v.location.distance_miles(&query.centroid) <= query.radius_miles)
+            .filter(|v| match (&query.category, &v.venue.category) {
+                (Some(wanted), Some(category)) => wanted.eq_ignore_ascii_case(category),
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .filter(|v| match (query.open_at, v.hours) {
+                (Some(at), Some(hours)) => hours.is_open_at(at),
+                _ => true,
+            })
+            .map(|v| v.venue.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [-122.6765, 45.5231] },
+                "properties": {
+                    "name": "Pioneer Taproom",
+                    "category": "bar",
+                    "vibe_tags": ["chill"],
+                    "hours": { "opens": "16:00:00", "closes": "02:00:00" }
+                }
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [-122.6750, 45.5200] },
+                "properties": {
+                    "name": "Morning Grind",
+                    "category": "cafe",
+                    "hours": { "opens": "07:00:00", "closes": "15:00:00" }
+                }
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [-122.3321, 47.6062] },
+                "properties": { "name": "Far Away Bar", "category": "bar" }
+            }
+        ]
+    }"#;
+
+    fn query(category: Option<&str>, open_at: Option<&str>) -> VenueQuery {
+        VenueQuery {
+            centroid: Location {
+                lat: 45.5220,
+                lng: -122.6760,
+            },
+            radius_miles: DEFAULT_RADIUS_MILES,
+            category: category.map(str::to_string),
+            open_at: open_at.map(|t| t.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_geojson_search_within_radius() {
+        let provider = FileVenueProvider::from_json(FIXTURE).unwrap();
+        assert_eq!(provider.len(), 3);
+
+        let venues = provider.search(&query(None, None));
+        assert_eq!(venues.len(), 2);
+        assert!(venues.iter().all(|v| v.source == VenueSource::Discovery));
+    }
+
+    #[test]
+    fn test_search_by_category_and_hours() {
+        let provider = FileVenueProvider::from_json(FIXTURE).unwrap();
+
+        let bars = provider.search(&query(Some("bar"), None));
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].name, "Pioneer Taproom");
+
+        // Open past midnight
+        let late = provider.search(&query(None, Some("2024-12-15T01:00:00Z")));
+        assert_eq!(late.len(), 1);
+        assert_eq!(late[0].name, "Pioneer Taproom");
+
+        let morning = provider.search(&query(None, Some("2024-12-15T09:00:00Z")));
+        assert_eq!(morning.len(), 1);
+        assert_eq!(morning[0].name, "Morning Grind");
+    }
+
+    #[test]
+    fn test_plain_json_array() {
+        let provider = FileVenueProvider::from_json(
+            r#"[{ "name": "Corner Cafe", "location": { "lat": 45.522, "lng": -122.676 } }]"#,
+        )
+        .unwrap();
+
+        let venues = provider.search(&query(None, None));
+        assert_eq!(venues.len(), 1);
+        assert_eq!(venues[0].name, "Corner Cafe");
+    }
+}
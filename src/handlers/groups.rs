@@ -9,8 +9,9 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::models::{
-    AppError, AppJson, CreateGroupRequest, Group, JoinGroupRequest, LeaveGroupRequest, Ping,
-    RegenerateInviteRequest,
+    AddFavoriteRequest, AppError, AppJson, CreateGroupRequest, CreatePingTemplateRequest,
+    DeletePingTemplateRequest, FavoriteVenue, Group, JoinGroupRequest, LeaveGroupRequest, Ping,
+    PingTemplate, RegenerateInviteRequest, UpdateGroupSettingsRequest,
 };
 use crate::state::AppState;
 
@@ -153,6 +154,225 @@ pub async fn regenerate_invite_code(
     Ok(Json(updated))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/groups/{id}/settings",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    request_body = UpdateGroupSettingsRequest,
+    responses(
+        (status = 200, description = "Group settings updated", body = Group),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Access denied", body = crate::models::ApiError),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn update_group_settings(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<UpdateGroupSettingsRequest>,
+) -> Result<Json<Group>, AppError> {
+    request.validate()?;
+
+    let group = state
+        .groups
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "Only group members can update group settings".to_string(),
+        ));
+    }
+
+    let updated = state
+        .groups
+        .update(&id, |g| g.update_settings(request.clone()))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/groups/{id}/favorites",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    request_body = AddFavoriteRequest,
+    responses(
+        (status = 201, description = "Venue saved to group favorites", body = FavoriteVenue),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Access denied", body = crate::models::ApiError),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn add_favorite(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<AddFavoriteRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request.validate()?;
+
+    let group = state
+        .groups
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "Only group members can add favorites".to_string(),
+        ));
+    }
+
+    let favorite = FavoriteVenue::new(request);
+    let favorite_clone = favorite.clone();
+
+    state
+        .groups
+        .update(&id, |g| g.add_favorite(favorite_clone))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(favorite)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/favorites",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    responses(
+        (status = 200, description = "Group favorite venues", body = Vec<FavoriteVenue>),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn list_favorites(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<FavoriteVenue>>, AppError> {
+    state
+        .groups
+        .get(&id)
+        .map(|g| Json(g.favorites))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/groups/{id}/templates",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    request_body = CreatePingTemplateRequest,
+    responses(
+        (status = 201, description = "Ping template saved to the group", body = PingTemplate),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Access denied", body = crate::models::ApiError),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn add_template(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<CreatePingTemplateRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request.validate()?;
+    request.validate_min_attendees()?;
+
+    let group = state
+        .groups
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "Only group members can add templates".to_string(),
+        ));
+    }
+
+    let template = PingTemplate::new(request);
+    let template_clone = template.clone();
+
+    state
+        .groups
+        .update(&id, |g| g.add_template(template_clone))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/templates",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    responses(
+        (status = 200, description = "Group ping templates", body = Vec<PingTemplate>),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn list_templates(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<PingTemplate>>, AppError> {
+    state
+        .groups
+        .get(&id)
+        .map(|g| Json(g.templates))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}/templates/{template_id}",
+    params(
+        ("id" = Uuid, Path, description = "Group ID"),
+        ("template_id" = Uuid, Path, description = "Template ID")
+    ),
+    request_body = DeletePingTemplateRequest,
+    responses(
+        (status = 204, description = "Template removed"),
+        (status = 403, description = "Access denied", body = crate::models::ApiError),
+        (status = 404, description = "Group or template not found", body = crate::models::ApiError)
+    ),
+    tag = "Groups"
+)]
+pub async fn delete_template(
+    State(state): State<AppState>,
+    Path((id, template_id)): Path<(Uuid, Uuid)>,
+    AppJson(request): AppJson<DeletePingTemplateRequest>,
+) -> Result<StatusCode, AppError> {
+    let group = state
+        .groups
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "Only group members can remove templates".to_string(),
+        ));
+    }
+
+    if group.find_template(template_id).is_none() {
+        return Err(AppError::NotFound("Template".to_string()));
+    }
+
+    state
+        .groups
+        .update(&id, |g| g.remove_template(template_id))
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListPingsQuery {
     pub state: Option<String>,
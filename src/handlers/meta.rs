@@ -0,0 +1,27 @@
+use axum::Json;
+
+use crate::transitions::{ATTENDEE_TRANSITIONS, AttendeeTransition, TRANSITIONS, Transition};
+
+#[utoipa::path(
+    get,
+    path = "/api/meta/transitions",
+    responses(
+        (status = 200, description = "Every allowed ping lifecycle transition", body = [Transition])
+    ),
+    tag = "Meta"
+)]
+pub async fn list_transitions() -> Json<&'static [Transition]> {
+    Json(TRANSITIONS)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meta/attendee-transitions",
+    responses(
+        (status = 200, description = "Every allowed attendee status change during a hangout", body = [AttendeeTransition])
+    ),
+    tag = "Meta"
+)]
+pub async fn list_attendee_transitions() -> Json<&'static [AttendeeTransition]> {
+    Json(ATTENDEE_TRANSITIONS)
+}
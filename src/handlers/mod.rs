@@ -1,4 +1,6 @@
 pub mod groups;
+pub mod meta;
 pub mod pings;
 pub mod responses;
+pub mod series;
 pub mod users;
@@ -4,50 +4,89 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
+use chrono::Utc;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::matching::MatchingEngine;
 use crate::models::{
-    AppError, AppJson, CancelPingRequest, ConfirmHangoutRequest, CreatePingRequest, MatchResults,
-    Ping, TriggerMatchRequest, UpdateAttendeeStatusRequest,
+    ActivatePingRequest, AppError, AppJson, CancelPingRequest, CompletePingRequest,
+    ConfirmHangoutRequest, CreatePingRequest, DecideJoinRequest, FavoriteVenue, FlagVenueRequest,
+    HangoutData, HangoutSummary, LateJoinRequest, MatchResults, Ping, PingWithWarnings,
+    RetryPingRequest, ReviewVenueRequest, Timeline, TriggerMatchRequest,
+    UpdateAttendeeStatusRequest, UpdatePingRequest, Venue, Warning, WithdrawRequest,
 };
 use crate::state::AppState;
 use crate::state_machine::StateMachine;
+use crate::transitions::TransitionError;
 
 #[utoipa::path(
     post,
     path = "/api/pings",
     request_body = CreatePingRequest,
     responses(
-        (status = 201, description = "Ping created successfully", body = Ping),
+        (status = 201, description = "Ping created successfully, or scheduled when send_at is set; warns when the initiator is alone in the group", body = PingWithWarnings),
         (status = 400, description = "Invalid request data", body = crate::models::ApiError),
-        (status = 403, description = "User not a member of the group", body = crate::models::ApiError)
+        (status = 403, description = "User not a member of the group", body = crate::models::ApiError),
+        (status = 404, description = "Group or template not found", body = crate::models::ApiError),
+        (status = 409, description = "Initiator already has an open ping for this activity in the group; existing_id points to it. Set force to create anyway", body = crate::models::ApiError)
     ),
     tag = "Pings"
 )]
 pub async fn create_ping(
     State(state): State<AppState>,
-    AppJson(request): AppJson<CreatePingRequest>,
+    AppJson(mut request): AppJson<CreatePingRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    request.validate()?;
-
-    // Verify user is a member of the group
     let group = state
         .groups
         .get(&request.group)
         .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
 
+    if let Some(template_id) = request.template_id {
+        let template = group
+            .find_template(template_id)
+            .ok_or_else(|| AppError::NotFound("Template".to_string()))?;
+        request.apply_template(template);
+    }
+
+    request.validate()?;
+    request.validate_send_at(Utc::now())?;
+    request.validate_min_attendees()?;
+
+    // Verify user is a member of the group
     if !group.is_member(request.initiator) {
         return Err(AppError::Forbidden(
             "User is not a member of the group".to_string(),
         ));
     }
 
+    if request.invitees.iter().any(|id| !group.is_member(*id)) {
+        return Err(AppError::BadRequest(
+            "Invitees must be members of the group".to_string(),
+        ));
+    }
+
+    // A list of just the initiator would leave no one to send to, and an
+    // empty list means the whole group
+    if !request.invitees.is_empty() && request.invitees.iter().all(|id| *id == request.initiator) {
+        return Err(AppError::BadRequest(
+            "Invitees must include someone other than the initiator".to_string(),
+        ));
+    }
+
+    let force = request.force;
     let ping = Ping::new(request);
-    state.pings.insert(ping.id, ping.clone());
 
-    Ok((StatusCode::CREATED, Json(ping)))
+    state
+        .pings
+        .try_insert(ping.id, ping.clone(), |p| !force && ping.duplicates(p))
+        .map_err(|existing| duplicate_of(&existing))?;
+    let warnings = Warning::solo_group(&group).into_iter().collect();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PingWithWarnings::new(ping, warnings)),
+    ))
 }
 
 #[utoipa::path(
@@ -73,6 +112,66 @@ pub async fn get_ping(
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/api/pings/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = UpdatePingRequest,
+    responses(
+        (status = 200, description = "Ping updated; existing responses are flagged possibly_stale when the activity, timing or vibe changed", body = Ping),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Only initiator can edit", body = crate::models::ApiError),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping is past gathering, send_at given for a ping already sent, capacity below the current yeses, or the new activity duplicates another open ping without force", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn update_ping(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<UpdatePingRequest>,
+) -> Result<Json<Ping>, AppError> {
+    request.validate()?;
+    request.validate_send_at(Utc::now())?;
+
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_edit(&ping, &request)?;
+
+    // Only a new activity can clash with another open ping
+    let check_duplicates = !request.force
+        && request
+            .activity_type
+            .as_ref()
+            .is_some_and(|a| !a.eq_ignore_ascii_case(&ping.activity_type));
+    let updated = state
+        .pings
+        .try_update_checked(
+            &id,
+            |p| {
+                // Re-check in case the ping moved on in the meantime
+                StateMachine::can_edit(p, &request)?;
+                p.edit(request);
+                StateMachine::promote_waitlisted(p);
+                Ok(())
+            },
+            |edited, other| {
+                if check_duplicates && edited.duplicates(other) {
+                    return Err(duplicate_of(other));
+                }
+                Ok(())
+            },
+        )?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    Ok(Json(updated))
+}
+
 #[utoipa::path(
     post,
     path = "/api/pings/{id}/cancel",
@@ -82,6 +181,7 @@ pub async fn get_ping(
     request_body = CancelPingRequest,
     responses(
         (status = 200, description = "Ping cancelled successfully", body = Ping),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
         (status = 403, description = "Only initiator can cancel", body = crate::models::ApiError),
         (status = 404, description = "Ping not found", body = crate::models::ApiError),
         (status = 409, description = "Ping already in terminal state", body = crate::models::ApiError)
@@ -93,6 +193,8 @@ pub async fn cancel_ping(
     Path(id): Path<Uuid>,
     AppJson(request): AppJson<CancelPingRequest>,
 ) -> Result<Json<Ping>, AppError> {
+    request.validate()?;
+
     let ping = state
         .pings
         .get(&id)
@@ -102,12 +204,81 @@ pub async fn cancel_ping(
 
     let updated = state
         .pings
-        .update(&id, StateMachine::transition_to_cancelled)
+        .try_update(&id, |p| {
+            StateMachine::transition_to_cancelled(p, request.user_id, request.reason)
+        })?
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
     Ok(Json(updated))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/retry",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = RetryPingRequest,
+    responses(
+        (status = 201, description = "New ping created from the original with new timing", body = Ping),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Only initiator can retry, and only while still in the group", body = crate::models::ApiError),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in no_match or cancelled state, nobody left to invite to a targeted retry, no respondents left to carry over, or the initiator already has an open ping for this activity without force", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn retry_ping(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<RetryPingRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request.validate()?;
+
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_retry(&ping, request.user_id)?;
+
+    let group = state
+        .groups
+        .get(&ping.group)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "User is not a member of the group".to_string(),
+        ));
+    }
+
+    // A targeted retry must never fall back to the whole group
+    let targeted = !ping.invitees.is_empty();
+    let carry_over = request.carry_over_respondents;
+    let force = request.force;
+    let mut retry = ping.retry(request);
+    // Only invite people who are still around
+    retry.invitees.retain(|user| group.is_member(*user));
+    retry.pre_invited.retain(|user| group.is_member(*user));
+    if targeted && retry.invitees.is_empty() {
+        return Err(AppError::Conflict(
+            "Nobody from the original ping is left to invite".to_string(),
+        ));
+    }
+    if carry_over && retry.pre_invited.is_empty() {
+        return Err(AppError::Conflict(
+            "Nobody who responded to the original ping is left to carry over".to_string(),
+        ));
+    }
+    state
+        .pings
+        .try_insert(retry.id, retry.clone(), |p| !force && retry.duplicates(p))
+        .map_err(|existing| duplicate_of(&existing))?;
+
+    Ok((StatusCode::CREATED, Json(retry)))
+}
+
 #[utoipa::path(
     post,
     path = "/api/pings/{id}/match",
@@ -116,7 +287,7 @@ pub async fn cancel_ping(
     ),
     request_body = TriggerMatchRequest,
     responses(
-        (status = 200, description = "Matching triggered successfully", body = Ping),
+        (status = 200, description = "Matching triggered successfully (venue_confirmed if a venue was auto-selected)", body = Ping),
         (status = 403, description = "Only initiator can trigger matching", body = crate::models::ApiError),
         (status = 404, description = "Ping not found", body = crate::models::ApiError),
         (status = 409, description = "Ping not in gathering state", body = crate::models::ApiError)
@@ -135,15 +306,40 @@ pub async fn trigger_match(
 
     StateMachine::can_trigger_match(&ping, request.user_id)?;
 
+    let group = state
+        .groups
+        .get(&ping.group)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
     // Calculate match results
-    let match_results = MatchingEngine::calculate_match(&ping);
+    let match_results = MatchingEngine::calculate_match(&ping, &state.match_context(&ping));
+
+    // Skip the vote when the group allows it and scoring is decisive
+    let auto_hangout = group
+        .settings
+        .auto_select_margin
+        .and_then(|margin| MatchingEngine::auto_select(&match_results, margin))
+        .zip(match_results.overlap.as_ref())
+        .map(|(option, overlap)| {
+            let timeline = Timeline {
+                start: overlap.start,
+                end: overlap.end,
+            };
+            StateMachine::create_hangout_data(&ping, timeline, Some(option.venue.clone()))
+        });
 
     // Transition state with match results embedded
     let updated = state
         .pings
-        .update(&id, |p| {
-            StateMachine::transition_to_matching(p, match_results)
-        })
+        .try_update(&id, |p| {
+            StateMachine::transition_to_matching(p, request.user_id, match_results)?;
+            match auto_hangout {
+                Some(hangout) => {
+                    StateMachine::transition_to_venue_confirmed(p, request.user_id, hangout)
+                }
+                None => Ok(()),
+            }
+        })?
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
     Ok(Json(updated))
@@ -176,7 +372,7 @@ pub async fn get_match_results(
         .lifecycle
         .match_results()
         .cloned()
-        .unwrap_or_else(|| MatchingEngine::calculate_match(&ping));
+        .unwrap_or_else(|| MatchingEngine::calculate_match(&ping, &state.match_context(&ping)));
 
     Ok(Json(match_results))
 }
@@ -189,9 +385,9 @@ pub async fn get_match_results(
     ),
     request_body = ConfirmHangoutRequest,
     responses(
-        (status = 201, description = "Hangout confirmed", body = Ping),
+        (status = 201, description = "Hangout confirmed; warnings list attendees with an overlapping hangout", body = PingWithWarnings),
         (status = 400, description = "Invalid request data", body = crate::models::ApiError),
-        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 404, description = "Ping or venue not found", body = crate::models::ApiError),
         (status = 409, description = "Ping not in matching state", body = crate::models::ApiError)
     ),
     tag = "Pings"
@@ -206,20 +402,333 @@ pub async fn confirm_hangout(
         .get(&id)
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
+    request.validate()?;
+    request.validate_venue_choice()?;
+
     StateMachine::can_confirm(&ping)?;
 
-    // Create hangout data
-    let hangout_data = StateMachine::create_hangout_data(&ping, request.timeline);
+    let venue = match (request.venue_id, request.custom_venue) {
+        (Some(venue_id), _) => Some(find_venue(&state, &ping, venue_id)?),
+        (None, Some(custom)) => Some(custom.into()),
+        (None, None) => None,
+    };
+
+    // Create hangout data, keeping attendees and timeline held over from a re-vote
+    let mut hangout_data = match ping.lifecycle.pending_hangout() {
+        Some(pending) => HangoutData {
+            timeline: request.timeline.unwrap_or_else(|| pending.timeline.clone()),
+            venue,
+            ..pending.clone()
+        },
+        None => {
+            let timeline = request
+                .timeline
+                .ok_or_else(|| AppError::BadRequest("timeline is required".to_string()))?;
+            StateMachine::create_hangout_data(&ping, timeline, venue)
+        }
+    };
+    if request.capacity.is_some() {
+        hangout_data.capacity = request.capacity;
+    }
 
     // Transition ping state
     let updated = state
         .pings
-        .update(&id, |p| {
-            StateMachine::transition_to_venue_confirmed(p, hangout_data)
+        .try_update(&id, |p| {
+            StateMachine::transition_to_venue_confirmed(p, request.user_id, hangout_data)?;
+            StateMachine::promote_waitlisted(p);
+            Ok::<_, TransitionError>(())
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    let mut participants = vec![updated.initiator];
+    if let Some(hangout) = updated.lifecycle.hangout() {
+        participants.extend(
+            hangout
+                .confirmed_attendees
+                .iter()
+                .filter(|id| **id != updated.initiator),
+        );
+    }
+    let warnings = state.conflict_warnings(&updated, &participants);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PingWithWarnings::new(updated, warnings)),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/venue-unavailable",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = FlagVenueRequest,
+    responses(
+        (status = 200, description = "Fallback venue selected (venue_confirmed) or re-vote opened (matching)", body = Ping),
+        (status = 403, description = "User not a member of the group", body = crate::models::ApiError),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping has no confirmed venue", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn flag_venue_unavailable(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<FlagVenueRequest>,
+) -> Result<Json<Ping>, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    let group = state
+        .groups
+        .get(&ping.group)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "User is not a member of the group".to_string(),
+        ));
+    }
+
+    StateMachine::can_flag_venue(&ping)?;
+
+    // Drop the closed venue from the options the group can pick from
+    let closed_id = ping
+        .lifecycle
+        .hangout()
+        .and_then(|h| h.venue.as_ref())
+        .map(|v| v.id);
+    let mut remaining = ping
+        .lifecycle
+        .match_results()
+        .cloned()
+        .unwrap_or_else(|| MatchingEngine::calculate_match(&ping, &state.match_context(&ping)));
+    remaining
+        .venue_options
+        .retain(|o| Some(o.venue.id) != closed_id);
+
+    // Fall back to the runner-up when it is the only option left or the
+    // group's auto-select margin says it is a clear winner; otherwise re-vote
+    let margin = group.settings.auto_select_margin.unwrap_or(f64::INFINITY);
+    let fallback = MatchingEngine::auto_select(&remaining, margin).map(|o| o.venue.clone());
+
+    let updated = state
+        .pings
+        .try_update(&id, |p| match fallback {
+            Some(venue) => {
+                StateMachine::transition_to_fallback_venue(p, request.user_id, remaining, venue)
+            }
+            None => StateMachine::transition_to_revote(p, request.user_id, remaining),
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+/// Look up a venue by id among the match's venue options, then the group's favorites.
+fn find_venue(state: &AppState, ping: &Ping, venue_id: Uuid) -> Result<Venue, AppError> {
+    let candidate = ping
+        .lifecycle
+        .match_results()
+        .and_then(|m| m.venue_options.iter().find(|o| o.venue.id == venue_id))
+        .map(|o| o.venue.clone());
+
+    candidate
+        .or_else(|| {
+            state.groups.get(&ping.group).and_then(|g| {
+                g.favorites
+                    .into_iter()
+                    .find(|f| f.venue.id == venue_id)
+                    .map(|f| f.venue)
+            })
         })
+        .ok_or_else(|| AppError::NotFound("Venue".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/withdraw",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = WithdrawRequest,
+    responses(
+        (status = 200, description = "Attendee withdrawn (the first waitlisted user takes the spot; ping cancelled if nobody is left attending)", body = Ping),
+        (status = 404, description = "Ping not found or user not an attendee", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in venue_confirmed state", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn withdraw(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<WithdrawRequest>,
+) -> Result<Json<Ping>, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
-    Ok((StatusCode::CREATED, Json(updated)))
+    StateMachine::can_withdraw(&ping, request.user_id)?;
+
+    let updated = state
+        .pings
+        .try_update(&id, |p| {
+            StateMachine::transition_to_withdrawn(p, request.user_id)
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/join-requests",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = LateJoinRequest,
+    responses(
+        (status = 200, description = "User joined the hangout; warnings list their overlapping hangouts", body = PingWithWarnings),
+        (status = 202, description = "Hangout is full; join request queued for the initiator", body = PingWithWarnings),
+        (status = 403, description = "User not a member of the group, or not invited to a targeted ping", body = crate::models::ApiError),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in venue_confirmed or active_hangout state, or user already attending or queued", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn request_join(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<LateJoinRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    let group = state
+        .groups
+        .get(&ping.group)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.user_id) {
+        return Err(AppError::Forbidden(
+            "User is not a member of the group".to_string(),
+        ));
+    }
+
+    StateMachine::can_request_join(&ping, request.user_id)?;
+
+    let updated = state
+        .pings
+        .try_update(&id, |p| {
+            // Re-check in case the ping moved on in the meantime
+            StateMachine::can_request_join(p, request.user_id)?;
+            StateMachine::transition_to_join_requested(p, request.user_id);
+            Ok::<_, AppError>(())
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    let joined = updated
+        .lifecycle
+        .hangout()
+        .is_some_and(|h| h.is_attendee(request.user_id));
+    let status = if joined {
+        StatusCode::OK
+    } else {
+        StatusCode::ACCEPTED
+    };
+    let warnings = state.conflict_warnings(&updated, &[request.user_id]);
+
+    Ok((status, Json(PingWithWarnings::new(updated, warnings))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/join-requests/{user_id}/approve",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID"),
+        ("user_id" = Uuid, Path, description = "User who asked to join")
+    ),
+    request_body = DecideJoinRequest,
+    responses(
+        (status = 200, description = "Join request approved; the user is attending. Warnings list their overlapping hangouts", body = PingWithWarnings),
+        (status = 403, description = "Only initiator can decide join requests", body = crate::models::ApiError),
+        (status = 404, description = "Ping or join request not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in venue_confirmed or active_hangout state", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn approve_join_request(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    AppJson(request): AppJson<DecideJoinRequest>,
+) -> Result<Json<PingWithWarnings>, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_decide_join_request(&ping, request.user_id, user_id)?;
+
+    let updated = state
+        .pings
+        .try_update(&id, |p| {
+            StateMachine::can_decide_join_request(p, request.user_id, user_id)?;
+            StateMachine::transition_to_joined(p, user_id);
+            Ok::<_, AppError>(())
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+    let warnings = state.conflict_warnings(&updated, &[user_id]);
+
+    Ok(Json(PingWithWarnings::new(updated, warnings)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/join-requests/{user_id}/deny",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID"),
+        ("user_id" = Uuid, Path, description = "User who asked to join")
+    ),
+    request_body = DecideJoinRequest,
+    responses(
+        (status = 200, description = "Join request denied", body = Ping),
+        (status = 403, description = "Only initiator can decide join requests", body = crate::models::ApiError),
+        (status = 404, description = "Ping or join request not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in venue_confirmed or active_hangout state", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn deny_join_request(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    AppJson(request): AppJson<DecideJoinRequest>,
+) -> Result<Json<Ping>, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_decide_join_request(&ping, request.user_id, user_id)?;
+
+    let updated = state
+        .pings
+        .try_update(&id, |p| {
+            StateMachine::can_decide_join_request(p, request.user_id, user_id)?;
+            if let Some(hangout) = p.lifecycle.hangout_mut() {
+                hangout.remove_join_request(user_id);
+            }
+            Ok::<_, AppError>(())
+        })?
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    Ok(Json(updated))
 }
 
 #[utoipa::path(
@@ -228,8 +737,10 @@ pub async fn confirm_hangout(
     params(
         ("id" = Uuid, Path, description = "Ping ID")
     ),
+    request_body = ActivatePingRequest,
     responses(
-        (status = 200, description = "Ping activated (hangout started)", body = Ping),
+        (status = 200, description = "Ping activated (hangout started early; the scheduler activates it at the confirmed start time)", body = Ping),
+        (status = 403, description = "Only initiator can activate, or a present attendee once the initiator has left or said no", body = crate::models::ApiError),
         (status = 404, description = "Ping not found", body = crate::models::ApiError),
         (status = 409, description = "Ping not in venue_confirmed state", body = crate::models::ApiError)
     ),
@@ -238,17 +749,20 @@ pub async fn confirm_hangout(
 pub async fn activate_ping(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    AppJson(request): AppJson<ActivatePingRequest>,
 ) -> Result<Json<Ping>, AppError> {
     let ping = state
         .pings
         .get(&id)
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
-    StateMachine::can_activate(&ping)?;
+    StateMachine::can_activate(&ping, request.user_id)?;
 
     let updated = state
         .pings
-        .update(&id, StateMachine::transition_to_active)
+        .try_update(&id, |p| {
+            StateMachine::transition_to_active(p, request.user_id)
+        })?
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
     Ok(Json(updated))
@@ -260,8 +774,10 @@ pub async fn activate_ping(
     params(
         ("id" = Uuid, Path, description = "Ping ID")
     ),
+    request_body = CompletePingRequest,
     responses(
-        (status = 200, description = "Ping completed", body = Ping),
+        (status = 200, description = "Ping completed (hangout ended early; it also completes once everyone has left or the grace period after the timeline end runs out)", body = Ping),
+        (status = 403, description = "Only initiator can complete, or a present attendee once the initiator has left or said no", body = crate::models::ApiError),
         (status = 404, description = "Ping not found", body = crate::models::ApiError),
         (status = 409, description = "Ping not in active_hangout state", body = crate::models::ApiError)
     ),
@@ -270,22 +786,140 @@ pub async fn activate_ping(
 pub async fn complete_ping(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    AppJson(request): AppJson<CompletePingRequest>,
 ) -> Result<Json<Ping>, AppError> {
     let ping = state
         .pings
         .get(&id)
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
-    StateMachine::can_complete(&ping)?;
+    StateMachine::can_complete(&ping, request.user_id)?;
 
     let updated = state
         .pings
-        .update(&id, StateMachine::transition_to_complete)
+        .try_update(&id, |p| {
+            StateMachine::transition_to_complete(p, request.user_id)
+        })?
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
+    state.record_favorite_visit(&updated);
+
     Ok(Json(updated))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/pings/{id}/summary",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    responses(
+        (status = 200, description = "Who actually came and for how long", body = HangoutSummary),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not in complete state", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn get_summary(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<HangoutSummary>, AppError> {
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_view_summary(&ping)?;
+
+    let hangout = ping
+        .lifecycle
+        .hangout()
+        .ok_or_else(|| AppError::Conflict("Ping does not have a hangout".to_string()))?;
+
+    Ok(Json(HangoutSummary::new(
+        ping.id,
+        hangout,
+        ping.lifecycle.entered_at(),
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/pings/{id}/venue-review",
+    params(
+        ("id" = Uuid, Path, description = "Ping ID")
+    ),
+    request_body = ReviewVenueRequest,
+    responses(
+        (status = 200, description = "Venue rated, noted or saved to favorites", body = FavoriteVenue),
+        (status = 400, description = "Invalid request data", body = crate::models::ApiError),
+        (status = 403, description = "Only attendees can review the venue", body = crate::models::ApiError),
+        (status = 404, description = "Ping not found", body = crate::models::ApiError),
+        (status = 409, description = "Ping not complete, has no venue, or venue is not a favorite", body = crate::models::ApiError)
+    ),
+    tag = "Pings"
+)]
+pub async fn review_venue(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<ReviewVenueRequest>,
+) -> Result<Json<FavoriteVenue>, AppError> {
+    request.validate()?;
+    request.validate_review()?;
+
+    let ping = state
+        .pings
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
+
+    StateMachine::can_review_venue(&ping, request.user_id)?;
+
+    let hangout = ping
+        .lifecycle
+        .hangout()
+        .ok_or_else(|| AppError::Conflict("Ping does not have a hangout".to_string()))?;
+    let venue = hangout
+        .venue
+        .clone()
+        .ok_or_else(|| AppError::Conflict("Hangout has no venue to review".to_string()))?;
+
+    let group = state
+        .groups
+        .get(&ping.group)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    let is_favorite = group.favorites.iter().any(|f| f.venue.id == venue.id);
+    if !is_favorite && !request.save_to_favorites {
+        return Err(AppError::Conflict(
+            "Venue is not a group favorite; set save_to_favorites to save it".to_string(),
+        ));
+    }
+
+    let visited_at = hangout.visited_at();
+    let mut reviewed = None;
+    state.groups.update(&ping.group, |g| {
+        if !is_favorite {
+            let mut favorite = FavoriteVenue::from_venue(venue.clone());
+            favorite.record_visit(visited_at);
+            g.add_favorite(favorite);
+        }
+
+        if let Some(favorite) = g.find_favorite_mut(venue.id) {
+            if let Some(rating) = request.rating {
+                favorite.rate(request.user_id, rating);
+            }
+            if let Some(note) = request.note.clone() {
+                favorite.add_note(request.user_id, note);
+            }
+            reviewed = Some(favorite.clone());
+        }
+    });
+
+    reviewed
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))
+}
+
 #[utoipa::path(
     put,
     path = "/api/pings/{id}/attendees/{user_id}/status",
@@ -295,9 +929,11 @@ pub async fn complete_ping(
     ),
     request_body = UpdateAttendeeStatusRequest,
     responses(
-        (status = 200, description = "Attendee status updated", body = Ping),
+        (status = 200, description = "Attendee status updated; the ping completes once every attendee has left", body = Ping),
+        (status = 400, description = "Invalid ETA or note", body = crate::models::ApiError),
+        (status = 403, description = "Attendees can only update their own status", body = crate::models::ApiError),
         (status = 404, description = "Ping not found or user not an attendee", body = crate::models::ApiError),
-        (status = 409, description = "Ping not in active_hangout or venue_confirmed state", body = crate::models::ApiError)
+        (status = 409, description = "Ping has no hangout or is already over, or the status change isn't allowed (e.g. arrived back to pending)", body = crate::models::ApiError)
     ),
     tag = "Pings"
 )]
@@ -306,30 +942,41 @@ pub async fn update_attendee_status(
     Path((ping_id, user_id)): Path<(Uuid, Uuid)>,
     AppJson(request): AppJson<UpdateAttendeeStatusRequest>,
 ) -> Result<Json<Ping>, AppError> {
-    let ping = state
-        .pings
-        .get(&ping_id)
-        .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
-
-    // Verify the ping has a hangout and the user is an attendee
-    let hangout = ping
-        .lifecycle
-        .hangout()
-        .ok_or_else(|| AppError::Conflict("Ping does not have an active hangout".to_string()))?;
+    request.validate()?;
+    let now = Utc::now();
+    request.validate_eta(now)?;
 
-    if !hangout.is_attendee(user_id) {
-        return Err(AppError::NotFound("Attendee".to_string()));
+    if !state.pings.exists(&ping_id) {
+        return Err(AppError::NotFound("Ping".to_string()));
     }
 
-    let status = request.status;
+    StateMachine::can_update_attendee_status(request.user_id, user_id)?;
+
+    // Re-checked under the write lock so concurrent updates can't skip a step
+    let change = request.into_change(now);
+    let mut completed = false;
     let updated = state
         .pings
-        .update(&ping_id, |p| {
-            if let Some(h) = p.lifecycle.hangout_mut() {
-                h.update_attendee_status(user_id, status);
-            }
-        })
+        .try_update(&ping_id, |p| {
+            completed = StateMachine::transition_attendee(p, user_id, change)?;
+            Ok::<_, AppError>(())
+        })?
         .ok_or_else(|| AppError::NotFound("Ping".to_string()))?;
 
+    if completed {
+        state.record_favorite_visit(&updated);
+    }
+
     Ok(Json(updated))
 }
+
+/// The error for a ping that would duplicate `existing` without `force`.
+fn duplicate_of(existing: &Ping) -> AppError {
+    AppError::Duplicate {
+        message: format!(
+            "You have an active ping for {}. Set force to create another",
+            existing.activity_type
+        ),
+        existing_id: existing.id,
+    }
+}
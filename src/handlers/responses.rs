@@ -19,9 +19,9 @@ use crate::state_machine::StateMachine;
     ),
     request_body = CreateResponseRequest,
     responses(
-        (status = 201, description = "Response submitted successfully", body = Response),
+        (status = 201, description = "Response submitted successfully (a yes beyond the ping's capacity joins the waitlist; a targeted ping ends as no_match once every invitee has said no)", body = Response),
         (status = 400, description = "Invalid request data", body = crate::models::ApiError),
-        (status = 403, description = "User not a member of the group", body = crate::models::ApiError),
+        (status = 403, description = "User not a member of the group, or not invited to a targeted ping", body = crate::models::ApiError),
         (status = 404, description = "Ping not found", body = crate::models::ApiError),
         (status = 409, description = "User already responded to this ping", body = crate::models::ApiError)
     ),
@@ -62,12 +62,20 @@ pub async fn create_response(
         ));
     }
 
+    if !ping.is_invited(request.user) {
+        return Err(AppError::Forbidden(
+            "User was not invited to this ping".to_string(),
+        ));
+    }
+
     let response = Response::new(request);
     let response_clone = response.clone();
+    let audience = ping.audience(&group);
 
-    state
-        .pings
-        .update(&ping_id, |p| p.add_response(response_clone));
+    state.pings.try_update(&ping_id, |p| {
+        StateMachine::add_response(p, response_clone)?;
+        StateMachine::decline_if_everyone_said_no(p, &audience, response.user)
+    })?;
 
     Ok((StatusCode::CREATED, Json(response)))
 }
@@ -81,7 +89,7 @@ pub async fn create_response(
     ),
     request_body = UpdateResponseRequest,
     responses(
-        (status = 200, description = "Response updated successfully", body = Response),
+        (status = 200, description = "Response updated successfully (a freed spot goes to the first waitlisted user; a targeted ping ends as no_match once every invitee has said no)", body = Response),
         (status = 400, description = "Invalid request data", body = crate::models::ApiError),
         (status = 403, description = "User can only update their own response", body = crate::models::ApiError),
         (status = 404, description = "Response not found", body = crate::models::ApiError)
@@ -115,14 +123,23 @@ pub async fn update_response(
         ));
     }
 
+    let audience = state
+        .groups
+        .get(&ping.group)
+        .map(|g| ping.audience(&g))
+        .unwrap_or_default();
+
     // Update the response
     let mut updated_response = None;
-    state.pings.update(&ping_id, |p| {
+    state.pings.try_update(&ping_id, |p| {
         if let Some(r) = p.find_response_mut(response_id) {
             r.update(request.clone());
             updated_response = Some(r.clone());
         }
-    });
+        p.sync_waitlist(request.user);
+        StateMachine::promote_waitlisted(p);
+        StateMachine::decline_if_everyone_said_no(p, &audience, request.user)
+    })?;
 
     updated_response
         .map(Json)
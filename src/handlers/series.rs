@@ -0,0 +1,263 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{
+    AppError, AppJson, CreatePingSeriesRequest, Ping, PingSeries, SeriesActionRequest,
+};
+use crate::state::AppState;
+
+#[utoipa::path(
+    post,
+    path = "/api/groups/{id}/series",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    request_body = CreatePingSeriesRequest,
+    responses(
+        (status = 201, description = "Series created; the first ping goes out at next_occurrence", body = PingSeries),
+        (status = 400, description = "Invalid request data, or the recurrence has no upcoming occurrences", body = crate::models::ApiError),
+        (status = 403, description = "User not a member of the group", body = crate::models::ApiError),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn create_series(
+    State(state): State<AppState>,
+    Path(group_id): Path<Uuid>,
+    AppJson(request): AppJson<CreatePingSeriesRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request.validate()?;
+    request.recurrence.validate_rule()?;
+
+    let group = state
+        .groups
+        .get(&group_id)
+        .ok_or_else(|| AppError::NotFound("Group".to_string()))?;
+
+    if !group.is_member(request.initiator) {
+        return Err(AppError::Forbidden(
+            "User is not a member of the group".to_string(),
+        ));
+    }
+
+    if request.invitees.iter().any(|id| !group.is_member(*id)) {
+        return Err(AppError::BadRequest(
+            "Invitees must be members of the group".to_string(),
+        ));
+    }
+
+    if !request.invitees.is_empty() && request.invitees.iter().all(|id| *id == request.initiator) {
+        return Err(AppError::BadRequest(
+            "Invitees must include someone other than the initiator".to_string(),
+        ));
+    }
+
+    let series = PingSeries::new(group_id, request, Utc::now());
+    if series.next_occurrence.is_none() {
+        return Err(AppError::BadRequest(
+            "Recurrence has no upcoming occurrences".to_string(),
+        ));
+    }
+
+    state.series.insert(series.id, series.clone());
+
+    Ok((StatusCode::CREATED, Json(series)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/series",
+    params(
+        ("id" = Uuid, Path, description = "Group ID")
+    ),
+    responses(
+        (status = 200, description = "Recurring series in the group", body = Vec<PingSeries>),
+        (status = 404, description = "Group not found", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn list_group_series(
+    State(state): State<AppState>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<Vec<PingSeries>>, AppError> {
+    if !state.groups.exists(&group_id) {
+        return Err(AppError::NotFound("Group".to_string()));
+    }
+
+    let mut series = state.series.filter(|s| s.group == group_id);
+    series.sort_by_key(|s| s.created_at);
+
+    Ok(Json(series))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/series/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Series ID")
+    ),
+    responses(
+        (status = 200, description = "Series found", body = PingSeries),
+        (status = 404, description = "Series not found", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn get_series(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PingSeries>, AppError> {
+    state
+        .series
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/series/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Series ID")
+    ),
+    request_body = SeriesActionRequest,
+    responses(
+        (status = 204, description = "Series deleted; pings it already created are kept"),
+        (status = 403, description = "Only initiator can delete the series", body = crate::models::ApiError),
+        (status = 404, description = "Series not found", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn delete_series(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<SeriesActionRequest>,
+) -> Result<StatusCode, AppError> {
+    let series = state
+        .series
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))?;
+
+    if series.initiator != request.user_id {
+        return Err(AppError::Forbidden(
+            "Only initiator can delete the series".to_string(),
+        ));
+    }
+
+    state.series.remove(&id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/series/{id}/pause",
+    params(
+        ("id" = Uuid, Path, description = "Series ID")
+    ),
+    request_body = SeriesActionRequest,
+    responses(
+        (status = 200, description = "Series paused; no pings are created until it is resumed", body = PingSeries),
+        (status = 403, description = "Only initiator can pause the series", body = crate::models::ApiError),
+        (status = 404, description = "Series not found", body = crate::models::ApiError),
+        (status = 409, description = "Series is already paused", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn pause_series(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<SeriesActionRequest>,
+) -> Result<Json<PingSeries>, AppError> {
+    let series = state
+        .series
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))?;
+
+    if series.initiator != request.user_id {
+        return Err(AppError::Forbidden(
+            "Only initiator can pause the series".to_string(),
+        ));
+    }
+    if series.paused {
+        return Err(AppError::Conflict("Series is already paused".to_string()));
+    }
+
+    let updated = state
+        .series
+        .update(&id, |s| s.pause())
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/series/{id}/resume",
+    params(
+        ("id" = Uuid, Path, description = "Series ID")
+    ),
+    request_body = SeriesActionRequest,
+    responses(
+        (status = 200, description = "Series resumed; occurrences missed while paused are skipped", body = PingSeries),
+        (status = 403, description = "Only initiator can resume the series", body = crate::models::ApiError),
+        (status = 404, description = "Series not found", body = crate::models::ApiError),
+        (status = 409, description = "Series is not paused", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn resume_series(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<SeriesActionRequest>,
+) -> Result<Json<PingSeries>, AppError> {
+    let series = state
+        .series
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))?;
+
+    if series.initiator != request.user_id {
+        return Err(AppError::Forbidden(
+            "Only initiator can resume the series".to_string(),
+        ));
+    }
+    if !series.paused {
+        return Err(AppError::Conflict("Series is not paused".to_string()));
+    }
+
+    let updated = state
+        .series
+        .update(&id, |s| s.resume(Utc::now()))
+        .ok_or_else(|| AppError::NotFound("Series".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/series/{id}/pings",
+    params(
+        ("id" = Uuid, Path, description = "Series ID")
+    ),
+    responses(
+        (status = 200, description = "Pings created by the series, oldest first", body = Vec<Ping>),
+        (status = 404, description = "Series not found", body = crate::models::ApiError)
+    ),
+    tag = "Series"
+)]
+pub async fn list_series_pings(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Ping>>, AppError> {
+    if !state.series.exists(&id) {
+        return Err(AppError::NotFound("Series".to_string()));
+    }
+
+    Ok(Json(state.get_series_pings(id)))
+}
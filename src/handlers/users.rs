@@ -7,7 +7,9 @@ use axum::{
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::models::{AppError, AppJson, CreateUserRequest, Group, UpdateUserRequest, User};
+use crate::models::{
+    AppError, AppJson, CreateUserRequest, Group, HangoutConflict, UpdateUserRequest, User,
+};
 use crate::state::AppState;
 
 #[utoipa::path(
@@ -129,3 +131,26 @@ pub async fn list_user_groups(
     let groups = state.get_user_groups(id);
     Ok(Json(groups))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/conflicts",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Confirmed or active hangouts of the user that overlap in time, across all their groups", body = Vec<HangoutConflict>),
+        (status = 404, description = "User not found", body = crate::models::ApiError)
+    ),
+    tag = "Users"
+)]
+pub async fn list_user_conflicts(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<HangoutConflict>>, AppError> {
+    if !state.users.exists(&id) {
+        return Err(AppError::NotFound("User".to_string()));
+    }
+
+    Ok(Json(state.user_conflicts(id)))
+}
@@ -1,9 +1,12 @@
+pub mod discovery;
 pub mod handlers;
 pub mod matching;
 pub mod models;
 pub mod router;
+pub mod scheduler;
 pub mod state;
 pub mod state_machine;
+pub mod transitions;
 
 pub use router::create_router;
 pub use state::AppState;
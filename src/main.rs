@@ -1,10 +1,10 @@
-use std::net::SocketAddr;
+use std::{env, net::SocketAddr, sync::Arc};
 
 use tokio::net::TcpListener;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use sens_o_matic::{AppState, create_router};
+use sens_o_matic::{AppState, create_router, discovery::FileVenueProvider, scheduler::Scheduler};
 
 #[tokio::main]
 async fn main() {
@@ -18,7 +18,18 @@ async fn main() {
         .init();
 
     // Create application state
-    let state = AppState::new();
+    let mut state = AppState::new();
+
+    // Offline venue discovery from a local GeoJSON/JSON file
+    if let Ok(path) = env::var("VENUES_FILE") {
+        let provider = FileVenueProvider::from_path(&path)
+            .unwrap_or_else(|e| panic!("failed to load venues from {path}: {e}"));
+        tracing::info!("Loaded {} venues from {}", provider.len(), path);
+        state = state.with_venue_provider(Arc::new(provider));
+    }
+
+    // Start time-driven lifecycle transitions
+    Scheduler::spawn(state.clone());
 
     // Create router
     let app = create_router(state)
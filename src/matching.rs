@@ -1,14 +1,41 @@
-use chrono::{DateTime, Utc};
+use std::{collections::HashMap, sync::Arc};
 
-use crate::models::{MatchResults, Ping, Response, TimeOverlap};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::discovery::{DEFAULT_RADIUS_MILES, VenueProvider, VenueQuery, category_for_activity};
+use crate::models::{
+    CapacityHint, FavoriteVenue, Location, MatchResults, Ping, Response, TimeOverlap, Venue,
+    VenueOption,
+};
 
 #[cfg(test)]
-use crate::models::PingLifecycle;
+use crate::models::{Actor, PingLifecycle};
+
+// Default scoring weights from the spec
+const W_LOCATION: f64 = 0.35;
+const W_PREFERENCE: f64 = 0.30;
+const W_FAMILIARITY: f64 = 0.25;
+const W_CAPACITY: f64 = 0.10;
+
+/// Average distance (miles) at which the location score bottoms out
+const LOCATION_FALLOFF_MILES: f64 = 5.0;
+/// Favorites visited this recently get a small variety penalty
+const RECENT_VISIT_DAYS: i64 = 7;
+const MAX_VENUE_OPTIONS: usize = 5;
+
+/// Group data the matching phase needs beyond the ping itself.
+#[derive(Default)]
+pub struct MatchContext {
+    pub favorites: Vec<FavoriteVenue>,
+    pub attendee_locations: HashMap<Uuid, Location>,
+    pub provider: Option<Arc<dyn VenueProvider>>,
+}
 
 pub struct MatchingEngine;
 
 impl MatchingEngine {
-    pub fn calculate_match(ping: &Ping) -> MatchResults {
+    pub fn calculate_match(ping: &Ping, context: &MatchContext) -> MatchResults {
         let positive_responses: Vec<&Response> = ping.positive_responses();
 
         // Need at least 1 person with a positive response and availability
@@ -18,12 +45,16 @@ impl MatchingEngine {
             .filter(|r| r.availability.is_some())
             .copied()
             .collect();
+        let too_few = ping
+            .min_attendees
+            .is_some_and(|min| responses_with_availability.len() < min as usize);
 
-        if responses_with_availability.is_empty() {
+        if responses_with_availability.is_empty() || too_few {
             return MatchResults {
                 ping_id: ping.id,
                 overlap: None,
                 has_match: false,
+                venue_options: Vec::new(),
             };
         }
 
@@ -31,10 +62,214 @@ impl MatchingEngine {
         let overlap = Self::find_overlap(&responses_with_availability);
         let has_match = overlap.is_some();
 
+        let venue_options = match &overlap {
+            Some(window) => Self::rank_venues(ping, &positive_responses, context, window),
+            None => Vec::new(),
+        };
+
         MatchResults {
             ping_id: ping.id,
             overlap,
             has_match,
+            venue_options,
+        }
+    }
+
+    /// Pick a venue without a vote when scoring is decisive: either a single
+    /// option survived filtering, or the top option leads the runner-up by
+    /// more than `margin`.
+    pub fn auto_select(match_results: &MatchResults, margin: f64) -> Option<&VenueOption> {
+        if !match_results.has_match {
+            return None;
+        }
+
+        match match_results.venue_options.as_slice() {
+            [only] => Some(only),
+            [top, runner_up, ..] if top.score - runner_up.score > margin => Some(top),
+            _ => None,
+        }
+    }
+
+    fn rank_venues(
+        ping: &Ping,
+        responses: &[&Response],
+        context: &MatchContext,
+        window: &TimeOverlap,
+    ) -> Vec<VenueOption> {
+        let discovered = Self::discover_venues(ping, responses, context, window);
+
+        let favorites = context.favorites.iter().map(|f| (&f.venue, Some(f)));
+        let mut options: Vec<VenueOption> = favorites
+            .chain(discovered.iter().map(|v| (v, None)))
+            .filter(|(venue, _)| Self::passes_filters(venue, responses, context))
+            .map(|(venue, favorite)| VenueOption {
+                venue: venue.clone(),
+                score: Self::score_venue(ping, venue, favorite, responses, context),
+            })
+            .collect();
+
+        options.sort_by(|a, b| b.score.total_cmp(&a.score));
+        options.truncate(MAX_VENUE_OPTIONS);
+        options
+    }
+
+    /// Ask the venue provider for places near the attendees that are open
+    /// when the hangout starts. Venues already saved as favorites are skipped.
+    fn discover_venues(
+        ping: &Ping,
+        responses: &[&Response],
+        context: &MatchContext,
+        window: &TimeOverlap,
+    ) -> Vec<Venue> {
+        let Some(provider) = &context.provider else {
+            return Vec::new();
+        };
+        let Some(centroid) = Self::centroid(&context.attendee_locations) else {
+            return Vec::new();
+        };
+
+        let radius_miles = responses
+            .iter()
+            .filter_map(|r| r.preferences.as_ref()?.max_distance)
+            .reduce(f64::min)
+            .unwrap_or(DEFAULT_RADIUS_MILES);
+
+        let query = VenueQuery {
+            centroid,
+            radius_miles,
+            category: category_for_activity(&ping.activity_type).map(str::to_string),
+            open_at: Some(window.start),
+        };
+
+        provider
+            .search(&query)
+            .into_iter()
+            .filter(|v| !context.favorites.iter().any(|f| f.venue.id == v.id))
+            .collect()
+    }
+
+    fn centroid(locations: &HashMap<Uuid, Location>) -> Option<Location> {
+        if locations.is_empty() {
+            return None;
+        }
+
+        let count = locations.len() as f64;
+        Some(Location {
+            lat: locations.values().map(|l| l.lat).sum::<f64>() / count,
+            lng: locations.values().map(|l| l.lng).sum::<f64>() / count,
+        })
+    }
+
+    fn passes_filters(venue: &Venue, responses: &[&Response], context: &MatchContext) -> bool {
+        responses.iter().all(|r| {
+            let Some(prefs) = &r.preferences else {
+                return true;
+            };
+
+            let excluded = prefs
+                .excluded_areas
+                .iter()
+                .flatten()
+                .any(|area| venue.in_area(area));
+
+            let too_far = match (
+                prefs.max_distance,
+                &venue.location,
+                context.attendee_locations.get(&r.user),
+            ) {
+                (Some(max), Some(venue_loc), Some(user_loc)) => {
+                    user_loc.distance_miles(venue_loc) > max
+                }
+                _ => false,
+            };
+
+            !excluded && !too_far
+        })
+    }
+
+    fn score_venue(
+        ping: &Ping,
+        venue: &Venue,
+        favorite: Option<&FavoriteVenue>,
+        responses: &[&Response],
+        context: &MatchContext,
+    ) -> f64 {
+        // Initiator is implicitly attending
+        let group_size = responses.len() + 1;
+
+        W_LOCATION * Self::location_score(venue, context)
+            + W_PREFERENCE * Self::preference_score(ping, venue, responses)
+            + W_FAMILIARITY * Self::familiarity_score(favorite)
+            + W_CAPACITY * Self::capacity_score(venue, group_size)
+    }
+
+    fn location_score(venue: &Venue, context: &MatchContext) -> f64 {
+        let Some(venue_loc) = &venue.location else {
+            return 0.5;
+        };
+        if context.attendee_locations.is_empty() {
+            return 0.5;
+        }
+
+        let total: f64 = context
+            .attendee_locations
+            .values()
+            .map(|loc| loc.distance_miles(venue_loc))
+            .sum();
+        let average = total / context.attendee_locations.len() as f64;
+
+        (1.0 - average / LOCATION_FALLOFF_MILES).clamp(0.0, 1.0)
+    }
+
+    fn preference_score(ping: &Ping, venue: &Venue, responses: &[&Response]) -> f64 {
+        let vibe = match &ping.vibe {
+            Some(vibe) if venue.has_vibe(vibe) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+
+        let preferred: Vec<&Vec<String>> = responses
+            .iter()
+            .filter_map(|r| r.preferences.as_ref()?.preferred_areas.as_ref())
+            .collect();
+        let area = if preferred.is_empty() {
+            0.5
+        } else {
+            let hits = preferred
+                .iter()
+                .filter(|areas| areas.iter().any(|a| venue.in_area(a)))
+                .count();
+            hits as f64 / preferred.len() as f64
+        };
+
+        (vibe + area) / 2.0
+    }
+
+    fn familiarity_score(favorite: Option<&FavoriteVenue>) -> f64 {
+        let Some(favorite) = favorite else {
+            return 0.0;
+        };
+
+        let rating = favorite.group_rating.map_or(0.5, |r| r / 5.0);
+        let mut score = 0.6 + 0.4 * rating;
+
+        if favorite
+            .last_visited
+            .is_some_and(|visited| Utc::now() - visited < Duration::days(RECENT_VISIT_DAYS))
+        {
+            score -= 0.2;
+        }
+
+        score.clamp(0.0, 1.0)
+    }
+
+    fn capacity_score(venue: &Venue, group_size: usize) -> f64 {
+        match (venue.capacity_hint, group_size) {
+            (None, _) => 0.5,
+            (Some(CapacityHint::Small), n) if n > 4 => 0.0,
+            (Some(CapacityHint::Medium), n) if n > 10 => 0.0,
+            (Some(CapacityHint::Large), n) if n <= 4 => 0.7,
+            _ => 1.0,
         }
     }
 
@@ -74,8 +309,8 @@ impl MatchingEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::Availability;
-    use uuid::Uuid;
+    use crate::models::{Availability, ResponsePreferences, VenueSource};
+    use std::sync::Mutex;
 
     fn create_test_response(user_id: Uuid, earliest: &str, latest: &str) -> Response {
         Response {
@@ -88,6 +323,7 @@ mod tests {
             }),
             preferences: None,
             updated_at: Utc::now(),
+            possibly_stale: false,
         }
     }
 
@@ -99,7 +335,19 @@ mod tests {
             activity_type: "drinks".to_string(),
             rough_timing: "tonight".to_string(),
             vibe: None,
-            lifecycle: PingLifecycle::Gathering { responses: vec![] },
+            capacity: None,
+            min_attendees: None,
+            waitlist: vec![],
+            invitees: vec![],
+            pre_invited: vec![],
+            retry_of: None,
+            series: None,
+            edited_at: None,
+            lifecycle: PingLifecycle::Gathering {
+                entered_at: Utc::now(),
+                actor: Actor::System,
+                responses: vec![],
+            },
             created_at: Utc::now(),
         }
     }
@@ -122,12 +370,12 @@ mod tests {
             "2024-12-15T22:00:00Z",
         );
 
-        if let PingLifecycle::Gathering { responses } = &mut ping.lifecycle {
+        if let PingLifecycle::Gathering { responses, .. } = &mut ping.lifecycle {
             responses.push(response1);
             responses.push(response2);
         }
 
-        let result = MatchingEngine::calculate_match(&ping);
+        let result = MatchingEngine::calculate_match(&ping, &MatchContext::default());
         assert!(result.has_match);
         assert!(result.overlap.is_some());
 
@@ -162,13 +410,231 @@ mod tests {
             "2024-12-15T23:00:00Z",
         );
 
-        if let PingLifecycle::Gathering { responses } = &mut ping.lifecycle {
+        if let PingLifecycle::Gathering { responses, .. } = &mut ping.lifecycle {
             responses.push(response1);
             responses.push(response2);
         }
 
-        let result = MatchingEngine::calculate_match(&ping);
+        let result = MatchingEngine::calculate_match(&ping, &MatchContext::default());
         assert!(!result.has_match);
         assert!(result.overlap.is_none());
     }
+
+    #[test]
+    fn test_min_attendees_not_reached() {
+        let mut ping = create_test_ping();
+        ping.min_attendees = Some(2);
+
+        let response = create_test_response(
+            Uuid::new_v4(),
+            "2024-12-15T17:00:00Z",
+            "2024-12-15T21:00:00Z",
+        );
+        if let PingLifecycle::Gathering { responses, .. } = &mut ping.lifecycle {
+            responses.push(response);
+        }
+
+        let result = MatchingEngine::calculate_match(&ping, &MatchContext::default());
+        assert!(!result.has_match);
+        assert!(result.overlap.is_none());
+    }
+
+    fn create_test_favorite(name: &str, vibe_tags: &[&str], area: Option<&str>) -> FavoriteVenue {
+        FavoriteVenue {
+            venue: Venue {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                location: None,
+                area: area.map(str::to_string),
+                category: None,
+                vibe_tags: vibe_tags.iter().map(|t| t.to_string()).collect(),
+                capacity_hint: None,
+                source: VenueSource::Favorite,
+            },
+            times_visited: 0,
+            last_visited: None,
+            group_rating: None,
+            ratings: HashMap::new(),
+            group_notes: Vec::new(),
+        }
+    }
+
+    fn create_matched_ping(vibe: Option<&str>, responses: Vec<Response>) -> Ping {
+        let mut ping = create_test_ping();
+        ping.vibe = vibe.map(str::to_string);
+        ping.lifecycle = PingLifecycle::Gathering {
+            entered_at: Utc::now(),
+            actor: Actor::System,
+            responses,
+        };
+        ping
+    }
+
+    fn overlapping_responses() -> Vec<Response> {
+        vec![
+            create_test_response(
+                Uuid::new_v4(),
+                "2024-12-15T17:00:00Z",
+                "2024-12-15T21:00:00Z",
+            ),
+            create_test_response(
+                Uuid::new_v4(),
+                "2024-12-15T18:00:00Z",
+                "2024-12-15T22:00:00Z",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_venues_ranked_by_vibe() {
+        let ping = create_matched_ping(Some("chill"), overlapping_responses());
+        let context = MatchContext {
+            favorites: vec![
+                create_test_favorite("Sidebar Lounge", &["upscale"], None),
+                create_test_favorite("The Rusty Nail", &["chill", "dive"], None),
+            ],
+            ..Default::default()
+        };
+
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        assert_eq!(result.venue_options.len(), 2);
+        assert_eq!(result.venue_options[0].venue.name, "The Rusty Nail");
+        assert!(result.venue_options[0].score > result.venue_options[1].score);
+    }
+
+    #[test]
+    fn test_excluded_area_filtered() {
+        let mut responses = overlapping_responses();
+        responses[0].preferences = Some(ResponsePreferences {
+            excluded_areas: Some(vec!["downtown".to_string()]),
+            ..Default::default()
+        });
+        let ping = create_matched_ping(None, responses);
+        let context = MatchContext {
+            favorites: vec![
+                create_test_favorite("Downtown Bar", &[], Some("Downtown")),
+                create_test_favorite("Murphy's Pub", &[], Some("Eastside")),
+            ],
+            ..Default::default()
+        };
+
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        assert_eq!(result.venue_options.len(), 1);
+        assert_eq!(result.venue_options[0].venue.name, "Murphy's Pub");
+    }
+
+    #[test]
+    fn test_auto_select_decisive_margin() {
+        let ping = create_matched_ping(Some("chill"), overlapping_responses());
+        let context = MatchContext {
+            favorites: vec![
+                create_test_favorite("Sidebar Lounge", &["upscale"], None),
+                create_test_favorite("The Rusty Nail", &["chill"], None),
+            ],
+            ..Default::default()
+        };
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        let lead = result.venue_options[0].score - result.venue_options[1].score;
+
+        let selected = MatchingEngine::auto_select(&result, lead - 0.01);
+        assert_eq!(selected.unwrap().venue.name, "The Rusty Nail");
+        assert!(MatchingEngine::auto_select(&result, lead + 0.01).is_none());
+    }
+
+    #[test]
+    fn test_auto_select_single_option() {
+        let ping = create_matched_ping(None, overlapping_responses());
+        let context = MatchContext {
+            favorites: vec![create_test_favorite("The Rusty Nail", &[], None)],
+            ..Default::default()
+        };
+
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        let selected = MatchingEngine::auto_select(&result, 1.0);
+        assert_eq!(selected.unwrap().venue.name, "The Rusty Nail");
+    }
+
+    #[test]
+    fn test_auto_select_requires_options() {
+        let ping = create_matched_ping(None, overlapping_responses());
+        let result = MatchingEngine::calculate_match(&ping, &MatchContext::default());
+        assert!(result.has_match);
+        assert!(MatchingEngine::auto_select(&result, 0.0).is_none());
+    }
+
+    struct StubProvider {
+        venues: Vec<Venue>,
+        last_query: Mutex<Option<VenueQuery>>,
+    }
+
+    impl VenueProvider for StubProvider {
+        fn search(&self, query: &VenueQuery) -> Vec<Venue> {
+            *self.last_query.lock().unwrap() = Some(query.clone());
+            self.venues.clone()
+        }
+    }
+
+    #[test]
+    fn test_discovered_venues_ranked_with_favorites() {
+        let mut responses = overlapping_responses();
+        responses[1].preferences = Some(ResponsePreferences {
+            max_distance: Some(2.0),
+            ..Default::default()
+        });
+        let mut ping = create_matched_ping(None, responses);
+        ping.activity_type = "drinks".to_string();
+
+        let mut discovered = create_test_favorite("Pioneer Taproom", &[], None).venue;
+        discovered.source = VenueSource::Discovery;
+        let provider = Arc::new(StubProvider {
+            venues: vec![discovered],
+            last_query: Mutex::new(None),
+        });
+
+        let context = MatchContext {
+            favorites: vec![create_test_favorite("The Rusty Nail", &[], None)],
+            attendee_locations: ping
+                .positive_responses()
+                .iter()
+                .map(|r| {
+                    (
+                        r.user,
+                        Location {
+                            lat: 45.5,
+                            lng: -122.6,
+                        },
+                    )
+                })
+                .collect(),
+            provider: Some(provider.clone()),
+        };
+
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        assert_eq!(result.venue_options.len(), 2);
+        // Familiarity puts the favorite first
+        assert_eq!(result.venue_options[0].venue.name, "The Rusty Nail");
+        assert_eq!(result.venue_options[1].venue.source, VenueSource::Discovery);
+
+        let query = provider.last_query.lock().unwrap().clone().unwrap();
+        assert_eq!(query.radius_miles, 2.0);
+        assert_eq!(query.category.as_deref(), Some("bar"));
+        assert_eq!(query.open_at, result.overlap.map(|o| o.start));
+    }
+
+    #[test]
+    fn test_discovery_needs_attendee_locations() {
+        let ping = create_matched_ping(None, overlapping_responses());
+        let provider = Arc::new(StubProvider {
+            venues: vec![create_test_favorite("Pioneer Taproom", &[], None).venue],
+            last_query: Mutex::new(None),
+        });
+        let context = MatchContext {
+            provider: Some(provider.clone()),
+            ..Default::default()
+        };
+
+        let result = MatchingEngine::calculate_match(&ping, &context);
+        assert!(result.venue_options.is_empty());
+        assert!(provider.last_query.lock().unwrap().is_none());
+    }
 }
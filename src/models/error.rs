@@ -6,11 +6,15 @@ use axum::{
 };
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ApiError {
     pub error: String,
     pub message: String,
+    /// The resource the request would have duplicated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub existing_id: Option<Uuid>,
 }
 
 /// Custom JSON extractor that returns 400 instead of 422 for JSON errors
@@ -37,6 +41,7 @@ impl ApiError {
         Self {
             error: error.into(),
             message: message.into(),
+            existing_id: None,
         }
     }
 
@@ -55,6 +60,13 @@ impl ApiError {
     pub fn conflict(message: impl Into<String>) -> Self {
         Self::new("conflict", message)
     }
+
+    pub fn duplicate(message: impl Into<String>, existing_id: Uuid) -> Self {
+        Self {
+            existing_id: Some(existing_id),
+            ..Self::new("duplicate", message)
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -71,6 +83,9 @@ pub enum AppError {
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    #[error("Duplicate: {message}")]
+    Duplicate { message: String, existing_id: Uuid },
+
     #[error("Validation error: {0}")]
     Validation(String),
 }
@@ -82,6 +97,13 @@ impl IntoResponse for AppError {
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, ApiError::bad_request(msg)),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, ApiError::forbidden(msg)),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, ApiError::conflict(msg)),
+            AppError::Duplicate {
+                message,
+                existing_id,
+            } => (
+                StatusCode::CONFLICT,
+                ApiError::duplicate(message, existing_id),
+            ),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, ApiError::bad_request(msg)),
         };
         (status, Json(error)).into_response()
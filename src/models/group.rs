@@ -4,12 +4,27 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::template::PingTemplate;
+use super::venue::FavoriteVenue;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+pub struct GroupSettings {
+    /// Minimum score lead the top venue needs over the runner-up for the
+    /// matching phase to confirm it without a vote. Disabled when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_select_margin: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Group {
     pub id: Uuid,
     pub name: String,
     pub members: Vec<Uuid>,
     pub invite_code: String,
+    pub favorites: Vec<FavoriteVenue>,
+    #[serde(default)]
+    pub templates: Vec<PingTemplate>,
+    pub settings: GroupSettings,
 }
 
 #[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
@@ -36,6 +51,14 @@ pub struct RegenerateInviteRequest {
     pub user_id: Uuid,
 }
 
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct UpdateGroupSettingsRequest {
+    pub user_id: Uuid,
+    #[validate(range(min = 0.0, max = 1.0))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_select_margin: Option<f64>,
+}
+
 use std::sync::LazyLock;
 static INVITE_CODE_REGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"^[A-Za-z0-9]{6,8}$").unwrap());
@@ -58,6 +81,9 @@ impl Group {
             name: request.name,
             members: vec![request.creator_id],
             invite_code: generate_invite_code(),
+            favorites: Vec::new(),
+            templates: Vec::new(),
+            settings: GroupSettings::default(),
         }
     }
 
@@ -78,4 +104,30 @@ impl Group {
     pub fn is_member(&self, user_id: Uuid) -> bool {
         self.members.contains(&user_id)
     }
+
+    pub fn update_settings(&mut self, request: UpdateGroupSettingsRequest) {
+        self.settings = GroupSettings {
+            auto_select_margin: request.auto_select_margin,
+        };
+    }
+
+    pub fn add_favorite(&mut self, favorite: FavoriteVenue) {
+        self.favorites.push(favorite);
+    }
+
+    pub fn find_favorite_mut(&mut self, venue_id: Uuid) -> Option<&mut FavoriteVenue> {
+        self.favorites.iter_mut().find(|f| f.venue.id == venue_id)
+    }
+
+    pub fn add_template(&mut self, template: PingTemplate) {
+        self.templates.push(template);
+    }
+
+    pub fn find_template(&self, template_id: Uuid) -> Option<&PingTemplate> {
+        self.templates.iter().find(|t| t.id == template_id)
+    }
+
+    pub fn remove_template(&mut self, template_id: Uuid) {
+        self.templates.retain(|t| t.id != template_id);
+    }
 }
@@ -1,25 +1,107 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::Validate;
+
+use super::error::AppError;
+use super::venue::{CustomVenue, Venue, VenueOption};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AttendeeStatus {
     Pending,
     Enroute,
+    /// Running late; usually comes with an ETA
+    Delayed,
     Arrived,
     Left,
 }
 
+impl AttendeeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttendeeStatus::Pending => "pending",
+            AttendeeStatus::Enroute => "enroute",
+            AttendeeStatus::Delayed => "delayed",
+            AttendeeStatus::Arrived => "arrived",
+            AttendeeStatus::Left => "left",
+        }
+    }
+
+    /// Statuses an ETA makes sense for
+    pub fn expects_eta(self) -> bool {
+        matches!(self, AttendeeStatus::Enroute | AttendeeStatus::Delayed)
+    }
+}
+
+impl fmt::Display for AttendeeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One entry in an attendee's status history
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatusChange {
+    pub status: AttendeeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl StatusChange {
+    pub fn new(status: AttendeeStatus) -> Self {
+        Self {
+            status,
+            eta: None,
+            note: None,
+            changed_at: Utc::now(),
+        }
+    }
+}
+
+/// When an attendee expects to arrive: a time, or a number of minutes from now
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum Eta {
+    At(DateTime<Utc>),
+    Minutes(u32),
+}
+
+/// Longest ETA accepted, in minutes
+pub const MAX_ETA_MINUTES: u32 = 24 * 60;
+
+impl Eta {
+    pub fn resolve(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Eta::At(at) => at,
+            Eta::Minutes(minutes) => now + TimeDelta::minutes(i64::from(minutes)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Timeline {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
 }
 
+impl Timeline {
+    /// The stretch both timelines share, if any; touching end to start
+    /// doesn't count.
+    pub fn overlap(&self, other: &Timeline) -> Option<Timeline> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Timeline { start, end })
+    }
+}
+
 /// Data associated with a hangout phase of a ping.
 /// This is a value object, not an entity - the Ping ID is the hangout identifier.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -27,17 +109,125 @@ pub struct HangoutData {
     pub confirmed_attendees: Vec<Uuid>,
     pub timeline: Timeline,
     pub attendee_statuses: HashMap<Uuid, AttendeeStatus>,
+    /// Expected arrival for attendees who are enroute or delayed
+    #[serde(default)]
+    pub attendee_etas: HashMap<Uuid, DateTime<Utc>>,
+    /// Latest free-text note from each attendee, e.g. "parking now"
+    #[serde(default)]
+    pub attendee_notes: HashMap<Uuid, String>,
+    /// Every status each attendee has had, oldest first
+    #[serde(default)]
+    pub status_history: HashMap<Uuid, Vec<StatusChange>>,
+    pub venue: Option<Venue>,
+    /// Most attendees the hangout takes; late joiners beyond it need the
+    /// initiator's approval
+    #[serde(default)]
+    pub capacity: Option<u32>,
+    #[serde(default)]
+    pub join_requests: Vec<JoinRequest>,
 }
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+/// Someone asking to join once the hangout is full
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JoinRequest {
+    pub user: Uuid,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Confirms the hangout, optionally choosing a venue either by id (a group
+/// favorite or one of the match's venue options) or as a free-form entry.
+/// The timeline may be omitted when re-confirming after a re-vote.
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
 pub struct ConfirmHangoutRequest {
     pub user_id: Uuid,
-    pub timeline: Timeline,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<Timeline>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub venue_id: Option<Uuid>,
+    #[validate(nested)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_venue: Option<CustomVenue>,
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<u32>,
+}
+
+impl ConfirmHangoutRequest {
+    pub fn validate_venue_choice(&self) -> Result<(), AppError> {
+        if self.venue_id.is_some() && self.custom_venue.is_some() {
+            return Err(AppError::BadRequest(
+                "Provide either venue_id or custom_venue, not both".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct FlagVenueRequest {
+    pub user_id: Uuid,
 }
 
 #[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct WithdrawRequest {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct LateJoinRequest {
+    pub user_id: Uuid,
+}
+
+/// Initiator approving or denying a queued join request
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct DecideJoinRequest {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
 pub struct UpdateAttendeeStatusRequest {
+    /// Must be the attendee whose status is being updated
+    pub user_id: Uuid,
     pub status: AttendeeStatus,
+    /// Only for enroute or delayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<Eta>,
+    #[validate(length(min = 1, max = 200))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl UpdateAttendeeStatusRequest {
+    pub fn validate_eta(&self, now: DateTime<Utc>) -> Result<(), AppError> {
+        let Some(eta) = self.eta else {
+            return Ok(());
+        };
+        if !self.status.expects_eta() {
+            return Err(AppError::BadRequest(format!(
+                "eta is only allowed when enroute or delayed, not {}",
+                self.status
+            )));
+        }
+        match eta {
+            Eta::Minutes(minutes) if minutes > MAX_ETA_MINUTES => Err(AppError::BadRequest(
+                format!("eta must be at most {MAX_ETA_MINUTES} minutes"),
+            )),
+            Eta::At(at) if at < now => Err(AppError::BadRequest(
+                "eta must not be in the past".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The history entry this update records
+    pub fn into_change(self, now: DateTime<Utc>) -> StatusChange {
+        StatusChange {
+            status: self.status,
+            eta: self.eta.map(|eta| eta.resolve(now)),
+            note: self.note,
+            changed_at: now,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -52,27 +242,107 @@ pub struct MatchResults {
     pub ping_id: Uuid,
     pub overlap: Option<TimeOverlap>,
     pub has_match: bool,
+    pub venue_options: Vec<VenueOption>,
 }
 
 impl HangoutData {
-    pub fn new(attendees: Vec<Uuid>, timeline: Timeline) -> Self {
+    pub fn new(attendees: Vec<Uuid>, timeline: Timeline, venue: Option<Venue>) -> Self {
         let attendee_statuses = attendees
             .iter()
             .map(|id| (*id, AttendeeStatus::Pending))
             .collect();
+        let status_history = attendees
+            .iter()
+            .map(|id| (*id, vec![StatusChange::new(AttendeeStatus::Pending)]))
+            .collect();
 
         Self {
             confirmed_attendees: attendees,
             timeline,
             attendee_statuses,
+            attendee_etas: HashMap::new(),
+            attendee_notes: HashMap::new(),
+            status_history,
+            venue,
+            capacity: None,
+            join_requests: Vec::new(),
+        }
+    }
+
+    /// When the hangout counts as a visit to its venue, however it ended
+    pub fn visited_at(&self) -> DateTime<Utc> {
+        self.timeline.end
+    }
+
+    pub fn add_attendee(&mut self, user_id: Uuid) {
+        if !self.is_attendee(user_id) {
+            self.confirmed_attendees.push(user_id);
+            self.attendee_statuses
+                .insert(user_id, AttendeeStatus::Pending);
+            self.status_history
+                .insert(user_id, vec![StatusChange::new(AttendeeStatus::Pending)]);
         }
     }
 
-    pub fn update_attendee_status(&mut self, user_id: Uuid, status: AttendeeStatus) {
-        self.attendee_statuses.insert(user_id, status);
+    pub fn attendee_status(&self, user_id: Uuid) -> Option<AttendeeStatus> {
+        self.attendee_statuses.get(&user_id).copied()
+    }
+
+    /// Apply a status change, replacing the attendee's ETA and note with
+    /// the new ones and adding it to their history. Callers check the move
+    /// is allowed first; see `StateMachine::transition_attendee`.
+    pub fn update_attendee_status(&mut self, user_id: Uuid, change: StatusChange) {
+        self.attendee_statuses.insert(user_id, change.status);
+        match change.eta {
+            Some(eta) => self.attendee_etas.insert(user_id, eta),
+            None => self.attendee_etas.remove(&user_id),
+        };
+        match &change.note {
+            Some(note) => self.attendee_notes.insert(user_id, note.clone()),
+            None => self.attendee_notes.remove(&user_id),
+        };
+        self.status_history.entry(user_id).or_default().push(change);
+    }
+
+    pub fn remove_attendee(&mut self, user_id: Uuid) {
+        self.confirmed_attendees.retain(|id| *id != user_id);
+        self.attendee_statuses.remove(&user_id);
+        self.attendee_etas.remove(&user_id);
+        self.attendee_notes.remove(&user_id);
+        self.status_history.remove(&user_id);
     }
 
     pub fn is_attendee(&self, user_id: Uuid) -> bool {
         self.confirmed_attendees.contains(&user_id)
     }
+
+    pub fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|c| self.confirmed_attendees.len() >= c as usize)
+    }
+
+    pub fn has_join_request(&self, user_id: Uuid) -> bool {
+        self.join_requests.iter().any(|r| r.user == user_id)
+    }
+
+    pub fn request_join(&mut self, user_id: Uuid) {
+        if !self.has_join_request(user_id) {
+            self.join_requests.push(JoinRequest {
+                user: user_id,
+                requested_at: Utc::now(),
+            });
+        }
+    }
+
+    pub fn remove_join_request(&mut self, user_id: Uuid) {
+        self.join_requests.retain(|r| r.user != user_id);
+    }
+
+    pub fn everyone_left(&self) -> bool {
+        !self.confirmed_attendees.is_empty()
+            && self
+                .confirmed_attendees
+                .iter()
+                .all(|id| self.attendee_statuses.get(id) == Some(&AttendeeStatus::Left))
+    }
 }
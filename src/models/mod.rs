@@ -3,18 +3,38 @@ pub mod group;
 pub mod hangout;
 pub mod ping;
 pub mod response;
+pub mod series;
+pub mod summary;
+pub mod template;
 pub mod user;
+pub mod venue;
+pub mod warning;
 
 pub use error::{ApiError, AppError, AppJson};
 pub use group::{
-    CreateGroupRequest, Group, JoinGroupRequest, LeaveGroupRequest, RegenerateInviteRequest,
+    CreateGroupRequest, Group, GroupSettings, JoinGroupRequest, LeaveGroupRequest,
+    RegenerateInviteRequest, UpdateGroupSettingsRequest,
 };
 pub use hangout::{
-    AttendeeStatus, ConfirmHangoutRequest, HangoutData, MatchResults, TimeOverlap, Timeline,
-    UpdateAttendeeStatusRequest,
+    AttendeeStatus, ConfirmHangoutRequest, DecideJoinRequest, Eta, FlagVenueRequest, HangoutData,
+    JoinRequest, LateJoinRequest, MatchResults, StatusChange, TimeOverlap, Timeline,
+    UpdateAttendeeStatusRequest, WithdrawRequest,
+};
+pub use ping::{
+    ActivatePingRequest, Actor, CancelPingRequest, CompletePingRequest, CreatePingRequest,
+    LifecycleState, Ping, PingLifecycle, RetryPingRequest, TriggerMatchRequest, UpdatePingRequest,
 };
-pub use ping::{CancelPingRequest, CreatePingRequest, Ping, PingLifecycle, TriggerMatchRequest};
 pub use response::{
     Availability, CreateResponseRequest, Response, ResponsePreferences, UpdateResponseRequest,
 };
+pub use series::{
+    CreatePingSeriesRequest, Frequency, PingSeries, Recurrence, SeriesActionRequest, Weekday,
+};
+pub use summary::{Attendance, HangoutSummary};
+pub use template::{CreatePingTemplateRequest, DeletePingTemplateRequest, PingTemplate};
 pub use user::{CreateUserRequest, Location, UpdateUserRequest, User, UserPreferences};
+pub use venue::{
+    AddFavoriteRequest, CapacityHint, CustomVenue, FavoriteVenue, ReviewVenueRequest, Venue,
+    VenueNote, VenueOption, VenueSource,
+};
+pub use warning::{HangoutConflict, PingWithWarnings, Warning};
@@ -1,116 +1,227 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::error::AppError;
+use super::group::Group;
 use super::hangout::{HangoutData, MatchResults};
 use super::response::Response;
+use super::template::PingTemplate;
 
+/// Each state records when the ping entered it and who moved it there.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "state", rename_all = "snake_case")]
 pub enum PingLifecycle {
-    PingSent,
+    /// Parked until `send_at`, when the scheduler sends it to the group
+    Scheduled {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
+        send_at: DateTime<Utc>,
+    },
+    PingSent {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
+    },
     Gathering {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
     },
     Matching {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
         match_results: MatchResults,
+        /// Hangout carried over while the group re-votes after the confirmed
+        /// venue fell through.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pending_hangout: Option<HangoutData>,
     },
     VenueConfirmed {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
+        match_results: MatchResults,
         hangout: HangoutData,
     },
     ActiveHangout {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
         hangout: HangoutData,
     },
     Complete {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
         hangout: HangoutData,
     },
     Cancelled {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
     },
     NoMatch {
+        entered_at: DateTime<Utc>,
+        actor: Actor,
         responses: Vec<Response>,
     },
 }
 
-impl PingLifecycle {
-    pub fn state_name(&self) -> &'static str {
+/// Who caused a lifecycle transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Actor {
+    User {
+        user_id: Uuid,
+    },
+    /// Time-driven transitions made by the scheduler
+    System,
+}
+
+/// A lifecycle state without its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleState {
+    Scheduled,
+    PingSent,
+    Gathering,
+    Matching,
+    VenueConfirmed,
+    ActiveHangout,
+    Complete,
+    Cancelled,
+    NoMatch,
+}
+
+impl LifecycleState {
+    pub const ALL: [LifecycleState; 9] = [
+        LifecycleState::Scheduled,
+        LifecycleState::PingSent,
+        LifecycleState::Gathering,
+        LifecycleState::Matching,
+        LifecycleState::VenueConfirmed,
+        LifecycleState::ActiveHangout,
+        LifecycleState::Complete,
+        LifecycleState::Cancelled,
+        LifecycleState::NoMatch,
+    ];
+
+    pub fn as_str(self) -> &'static str {
         match self {
-            PingLifecycle::PingSent => "ping_sent",
-            PingLifecycle::Gathering { .. } => "gathering",
-            PingLifecycle::Matching { .. } => "matching",
-            PingLifecycle::VenueConfirmed { .. } => "venue_confirmed",
-            PingLifecycle::ActiveHangout { .. } => "active_hangout",
-            PingLifecycle::Complete { .. } => "complete",
-            PingLifecycle::Cancelled { .. } => "cancelled",
-            PingLifecycle::NoMatch { .. } => "no_match",
+            LifecycleState::Scheduled => "scheduled",
+            LifecycleState::PingSent => "ping_sent",
+            LifecycleState::Gathering => "gathering",
+            LifecycleState::Matching => "matching",
+            LifecycleState::VenueConfirmed => "venue_confirmed",
+            LifecycleState::ActiveHangout => "active_hangout",
+            LifecycleState::Complete => "complete",
+            LifecycleState::Cancelled => "cancelled",
+            LifecycleState::NoMatch => "no_match",
         }
     }
 
-    pub fn is_terminal(&self) -> bool {
+    pub fn is_terminal(self) -> bool {
         matches!(
             self,
-            PingLifecycle::Complete { .. }
-                | PingLifecycle::Cancelled { .. }
-                | PingLifecycle::NoMatch { .. }
+            LifecycleState::Complete | LifecycleState::Cancelled | LifecycleState::NoMatch
         )
     }
+}
 
-    pub fn can_add_response(&self) -> bool {
-        matches!(
-            self,
-            PingLifecycle::PingSent | PingLifecycle::Gathering { .. }
-        )
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
     }
+}
 
-    pub fn can_trigger_match(&self) -> bool {
-        matches!(self, PingLifecycle::Gathering { .. })
+impl PingLifecycle {
+    pub fn state(&self) -> LifecycleState {
+        match self {
+            PingLifecycle::Scheduled { .. } => LifecycleState::Scheduled,
+            PingLifecycle::PingSent { .. } => LifecycleState::PingSent,
+            PingLifecycle::Gathering { .. } => LifecycleState::Gathering,
+            PingLifecycle::Matching { .. } => LifecycleState::Matching,
+            PingLifecycle::VenueConfirmed { .. } => LifecycleState::VenueConfirmed,
+            PingLifecycle::ActiveHangout { .. } => LifecycleState::ActiveHangout,
+            PingLifecycle::Complete { .. } => LifecycleState::Complete,
+            PingLifecycle::Cancelled { .. } => LifecycleState::Cancelled,
+            PingLifecycle::NoMatch { .. } => LifecycleState::NoMatch,
+        }
     }
 
-    pub fn can_confirm(&self) -> bool {
-        matches!(self, PingLifecycle::Matching { .. })
+    pub fn state_name(&self) -> &'static str {
+        self.state().as_str()
     }
 
-    pub fn can_activate(&self) -> bool {
-        matches!(self, PingLifecycle::VenueConfirmed { .. })
+    pub fn is_terminal(&self) -> bool {
+        self.state().is_terminal()
     }
 
-    pub fn can_complete(&self) -> bool {
-        matches!(self, PingLifecycle::ActiveHangout { .. })
+    pub fn entered_at(&self) -> DateTime<Utc> {
+        match self {
+            PingLifecycle::Scheduled { entered_at, .. }
+            | PingLifecycle::PingSent { entered_at, .. }
+            | PingLifecycle::Gathering { entered_at, .. }
+            | PingLifecycle::Matching { entered_at, .. }
+            | PingLifecycle::VenueConfirmed { entered_at, .. }
+            | PingLifecycle::ActiveHangout { entered_at, .. }
+            | PingLifecycle::Complete { entered_at, .. }
+            | PingLifecycle::Cancelled { entered_at, .. }
+            | PingLifecycle::NoMatch { entered_at, .. } => *entered_at,
+        }
     }
 
-    pub fn can_cancel(&self) -> bool {
-        !self.is_terminal()
+    pub fn actor(&self) -> Actor {
+        match self {
+            PingLifecycle::Scheduled { actor, .. }
+            | PingLifecycle::PingSent { actor, .. }
+            | PingLifecycle::Gathering { actor, .. }
+            | PingLifecycle::Matching { actor, .. }
+            | PingLifecycle::VenueConfirmed { actor, .. }
+            | PingLifecycle::ActiveHangout { actor, .. }
+            | PingLifecycle::Complete { actor, .. }
+            | PingLifecycle::Cancelled { actor, .. }
+            | PingLifecycle::NoMatch { actor, .. } => *actor,
+        }
+    }
+
+    pub fn can_retry(&self) -> bool {
+        matches!(
+            self,
+            PingLifecycle::NoMatch { .. } | PingLifecycle::Cancelled { .. }
+        )
     }
 
     pub fn responses(&self) -> &[Response] {
         match self {
-            PingLifecycle::PingSent => &[],
-            PingLifecycle::Gathering { responses }
+            PingLifecycle::Scheduled { .. } | PingLifecycle::PingSent { .. } => &[],
+            PingLifecycle::Gathering { responses, .. }
             | PingLifecycle::Matching { responses, .. }
             | PingLifecycle::VenueConfirmed { responses, .. }
             | PingLifecycle::ActiveHangout { responses, .. }
             | PingLifecycle::Complete { responses, .. }
-            | PingLifecycle::Cancelled { responses }
-            | PingLifecycle::NoMatch { responses } => responses,
+            | PingLifecycle::Cancelled { responses, .. }
+            | PingLifecycle::NoMatch { responses, .. } => responses,
         }
     }
 
     pub fn responses_mut(&mut self) -> Option<&mut Vec<Response>> {
         match self {
-            PingLifecycle::PingSent => None,
-            PingLifecycle::Gathering { responses }
+            PingLifecycle::Scheduled { .. } | PingLifecycle::PingSent { .. } => None,
+            PingLifecycle::Gathering { responses, .. }
             | PingLifecycle::Matching { responses, .. }
             | PingLifecycle::VenueConfirmed { responses, .. }
             | PingLifecycle::ActiveHangout { responses, .. }
             | PingLifecycle::Complete { responses, .. }
-            | PingLifecycle::Cancelled { responses }
-            | PingLifecycle::NoMatch { responses } => Some(responses),
+            | PingLifecycle::Cancelled { responses, .. }
+            | PingLifecycle::NoMatch { responses, .. } => Some(responses),
         }
     }
 
@@ -134,7 +245,24 @@ impl PingLifecycle {
 
     pub fn match_results(&self) -> Option<&MatchResults> {
         match self {
-            PingLifecycle::Matching { match_results, .. } => Some(match_results),
+            PingLifecycle::Matching { match_results, .. }
+            | PingLifecycle::VenueConfirmed { match_results, .. } => Some(match_results),
+            _ => None,
+        }
+    }
+
+    pub fn send_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            PingLifecycle::Scheduled { send_at, .. } => Some(*send_at),
+            _ => None,
+        }
+    }
+
+    pub fn pending_hangout(&self) -> Option<&HangoutData> {
+        match self {
+            PingLifecycle::Matching {
+                pending_hangout, ..
+            } => pending_hangout.as_ref(),
             _ => None,
         }
     }
@@ -148,6 +276,30 @@ pub struct Ping {
     pub activity_type: String,
     pub rough_timing: String,
     pub vibe: Option<String>,
+    pub capacity: Option<u32>,
+    /// Fewest available yeses matching needs before it finds a time
+    #[serde(default)]
+    pub min_attendees: Option<u32>,
+    /// Users who said yes after the ping filled up, first come first served
+    #[serde(default)]
+    pub waitlist: Vec<Uuid>,
+    /// Members the ping was sent to; empty means the whole group. Only
+    /// invitees can respond to a targeted ping.
+    #[serde(default)]
+    pub invitees: Vec<Uuid>,
+    /// Respondents carried over from the ping this one retries, invited
+    /// again up front
+    #[serde(default)]
+    pub pre_invited: Vec<Uuid>,
+    /// The no-match or cancelled ping this one retries
+    #[serde(default)]
+    pub retry_of: Option<Uuid>,
+    /// The recurring series that created this ping
+    #[serde(default)]
+    pub series: Option<Uuid>,
+    /// Last time the initiator changed the activity, timing or vibe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     #[serde(flatten)]
     pub lifecycle: PingLifecycle,
@@ -157,18 +309,133 @@ pub struct Ping {
 pub struct CreatePingRequest {
     pub initiator: Uuid,
     pub group: Uuid,
+    /// Fill any fields left out from one of the group's templates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<Uuid>,
+    /// Required unless the template provides it
     #[validate(length(min = 1, max = 50))]
+    #[serde(default)]
     pub activity_type: String,
+    /// Required unless the template provides it
     #[validate(length(min = 1, max = 50))]
+    #[serde(default)]
     pub rough_timing: String,
     #[validate(length(max = 100))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vibe: Option<String>,
+    /// Most people who can say yes; later yeses go on the waitlist
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<u32>,
+    /// Fewest available yeses matching needs before it finds a time
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_attendees: Option<u32>,
+    /// Create the ping even if the initiator already has an open one for the
+    /// same activity in the group
+    #[serde(default)]
+    pub force: bool,
+    /// Send to just these members instead of the whole group
+    #[serde(default)]
+    pub invitees: Vec<Uuid>,
+    /// Hold the ping back and send it to the group at this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+impl CreatePingRequest {
+    /// Take the template's value for every field the request left out.
+    pub fn apply_template(&mut self, template: &PingTemplate) {
+        if self.activity_type.is_empty() {
+            self.activity_type = template.activity_type.clone();
+        }
+        if self.rough_timing.is_empty()
+            && let Some(rough_timing) = &template.rough_timing
+        {
+            self.rough_timing = rough_timing.clone();
+        }
+        if self.vibe.is_none() {
+            self.vibe = template.vibe.clone();
+        }
+        if self.capacity.is_none() {
+            self.capacity = template.capacity;
+        }
+        if self.min_attendees.is_none() {
+            self.min_attendees = template.min_attendees;
+        }
+    }
+
+    pub fn validate_min_attendees(&self) -> Result<(), AppError> {
+        validate_min_attendees(self.min_attendees, self.capacity)
+    }
+
+    pub fn validate_send_at(&self, now: DateTime<Utc>) -> Result<(), AppError> {
+        validate_send_at(self.send_at, now)
+    }
+}
+
+/// Fix a ping's details while it is still scheduled or gathering. Omitted
+/// fields are left as they are.
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct UpdatePingRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 50))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity_type: Option<String>,
+    #[validate(length(min = 1, max = 50))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rough_timing: Option<String>,
+    /// An empty string clears the vibe
+    #[validate(length(max = 100))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vibe: Option<String>,
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<u32>,
+    /// Only while the ping is scheduled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send_at: Option<DateTime<Utc>>,
+    /// Change the activity even if the initiator already has an open ping
+    /// for the new one in the group
+    #[serde(default)]
+    pub force: bool,
+}
+
+impl UpdatePingRequest {
+    pub fn validate_send_at(&self, now: DateTime<Utc>) -> Result<(), AppError> {
+        validate_send_at(self.send_at, now)
+    }
+}
+
+/// A ping can't need more people than it has room for.
+pub fn validate_min_attendees(
+    min_attendees: Option<u32>,
+    capacity: Option<u32>,
+) -> Result<(), AppError> {
+    match min_attendees.zip(capacity) {
+        Some((min, capacity)) if min > capacity => Err(AppError::BadRequest(
+            "min_attendees must not be more than capacity".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_send_at(send_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), AppError> {
+    match send_at {
+        Some(at) if at <= now => Err(AppError::BadRequest(
+            "send_at must be in the future".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
 pub struct CancelPingRequest {
     pub user_id: Uuid,
+    /// Shown to the group alongside the cancellation
+    #[validate(length(min = 1, max = 200))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, ToSchema)]
@@ -176,8 +443,44 @@ pub struct TriggerMatchRequest {
     pub user_id: Uuid,
 }
 
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct RetryPingRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 50))]
+    pub rough_timing: String,
+    /// Pre-invite everyone who responded to the original ping. The retry
+    /// still goes to the original ping's whole audience.
+    #[serde(default)]
+    pub carry_over_respondents: bool,
+    /// Retry even if the initiator already has an open ping for the same
+    /// activity in the group
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ActivatePingRequest {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CompletePingRequest {
+    pub user_id: Uuid,
+}
+
 impl Ping {
     pub fn new(request: CreatePingRequest) -> Self {
+        let now = Utc::now();
+        let actor = Actor::User {
+            user_id: request.initiator,
+        };
+        let mut invitees = Vec::new();
+        for id in request.invitees {
+            if id != request.initiator && !invitees.contains(&id) {
+                invitees.push(id);
+            }
+        }
+
         Self {
             id: Uuid::new_v4(),
             initiator: request.initiator,
@@ -185,25 +488,129 @@ impl Ping {
             activity_type: request.activity_type,
             rough_timing: request.rough_timing,
             vibe: request.vibe,
-            created_at: Utc::now(),
-            lifecycle: PingLifecycle::PingSent,
+            capacity: request.capacity,
+            min_attendees: request.min_attendees,
+            waitlist: Vec::new(),
+            invitees,
+            pre_invited: Vec::new(),
+            retry_of: None,
+            series: None,
+            edited_at: None,
+            created_at: now,
+            lifecycle: match request.send_at {
+                Some(send_at) => PingLifecycle::Scheduled {
+                    entered_at: now,
+                    actor,
+                    send_at,
+                },
+                None => PingLifecycle::PingSent {
+                    entered_at: now,
+                    actor,
+                },
+            },
         }
     }
 
-    pub fn add_response(&mut self, response: Response) {
-        match &mut self.lifecycle {
-            PingLifecycle::PingSent => {
-                self.lifecycle = PingLifecycle::Gathering {
-                    responses: vec![response],
-                };
-            }
-            PingLifecycle::Gathering { responses } => {
-                responses.push(response);
+    /// Apply the fields set in `request`, leaving the rest as they are.
+    /// Answers given before the activity, timing or vibe changed are
+    /// flagged as possibly stale.
+    pub fn edit(&mut self, request: UpdatePingRequest) {
+        let mut changed = false;
+        if let Some(activity_type) = request.activity_type {
+            changed |= activity_type != self.activity_type;
+            self.activity_type = activity_type;
+        }
+        if let Some(rough_timing) = request.rough_timing {
+            changed |= rough_timing != self.rough_timing;
+            self.rough_timing = rough_timing;
+        }
+        if let Some(vibe) = request.vibe {
+            let vibe = (!vibe.is_empty()).then_some(vibe);
+            changed |= vibe != self.vibe;
+            self.vibe = vibe;
+        }
+        if changed {
+            self.edited_at = Some(Utc::now());
+            for response in self.lifecycle.responses_mut().into_iter().flatten() {
+                response.possibly_stale = true;
             }
-            _ => {}
+        }
+        if request.capacity.is_some() {
+            self.capacity = request.capacity;
+        }
+        if let Some(at) = request.send_at
+            && let PingLifecycle::Scheduled { send_at, .. } = &mut self.lifecycle
+        {
+            *send_at = at;
+        }
+    }
+
+    /// Start over from a ping that didn't come together, with new timing.
+    /// It goes to the same people, with the original respondents
+    /// pre-invited if asked.
+    pub fn retry(&self, request: RetryPingRequest) -> Self {
+        let pre_invited = if request.carry_over_respondents {
+            self.responses()
+                .iter()
+                .map(|r| r.user)
+                .filter(|user| *user != self.initiator)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            retry_of: Some(self.id),
+            pre_invited,
+            ..Self::new(CreatePingRequest {
+                initiator: self.initiator,
+                group: self.group,
+                template_id: None,
+                activity_type: self.activity_type.clone(),
+                rough_timing: request.rough_timing,
+                vibe: self.vibe.clone(),
+                capacity: self.capacity,
+                min_attendees: self.min_attendees,
+                force: false,
+                invitees: self.invitees.clone(),
+                send_at: None,
+            })
         }
     }
 
+    pub fn is_invited(&self, user_id: Uuid) -> bool {
+        self.invitees.is_empty() || self.invitees.contains(&user_id)
+    }
+
+    /// Everyone expected to respond: the invitees, or the rest of the group.
+    pub fn audience(&self, group: &Group) -> Vec<Uuid> {
+        if !self.invitees.is_empty() {
+            return self.invitees.clone();
+        }
+        group
+            .members
+            .iter()
+            .copied()
+            .filter(|id| *id != self.initiator)
+            .collect()
+    }
+
+    pub fn all_responded(&self, audience: &[Uuid]) -> bool {
+        !audience.is_empty() && audience.iter().all(|id| self.has_user_responded(*id))
+    }
+
+    /// Another still-open ping by the same initiator in the same group
+    /// for the same activity
+    pub fn duplicates(&self, other: &Ping) -> bool {
+        other.id != self.id
+            && !other.lifecycle.is_terminal()
+            && other.initiator == self.initiator
+            && other.group == self.group
+            && other
+                .activity_type
+                .eq_ignore_ascii_case(&self.activity_type)
+    }
+
     pub fn responses(&self) -> &[Response] {
         self.lifecycle.responses()
     }
@@ -222,7 +629,35 @@ impl Ping {
         self.responses().iter().any(|r| r.user == user_id)
     }
 
+    /// Yes responses holding a spot; waitlisted users aren't counted
     pub fn positive_responses(&self) -> Vec<&Response> {
-        self.responses().iter().filter(|r| r.answer).collect()
+        self.responses()
+            .iter()
+            .filter(|r| r.answer && !self.is_waitlisted(r.user))
+            .collect()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|c| self.positive_responses().len() >= c as usize)
+    }
+
+    pub fn is_waitlisted(&self, user_id: Uuid) -> bool {
+        self.waitlist.contains(&user_id)
+    }
+
+    /// Keep a user's place on the waitlist in step with their answer after
+    /// it changed: a new yes over capacity joins the back, a no leaves.
+    pub fn sync_waitlist(&mut self, user_id: Uuid) {
+        let answer = self.find_response(user_id).is_some_and(|r| r.answer);
+        if !answer {
+            self.waitlist.retain(|id| *id != user_id);
+        } else if !self.is_waitlisted(user_id)
+            && self
+                .capacity
+                .is_some_and(|c| self.positive_responses().len() > c as usize)
+        {
+            self.waitlist.push(user_id);
+        }
     }
 }
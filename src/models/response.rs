@@ -41,6 +41,10 @@ pub struct Response {
     pub availability: Option<Availability>,
     pub preferences: Option<ResponsePreferences>,
     pub updated_at: DateTime<Utc>,
+    /// The ping's details changed after this answer was given; cleared once
+    /// the user updates it
+    #[serde(default)]
+    pub possibly_stale: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
@@ -90,6 +94,7 @@ impl Response {
             availability: request.availability,
             preferences: request.preferences,
             updated_at: Utc::now(),
+            possibly_stale: false,
         }
     }
 
@@ -103,6 +108,17 @@ impl Response {
         if request.preferences.is_some() {
             self.preferences = request.preferences;
         }
+        self.possibly_stale = false;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn accept(&mut self) {
+        self.answer = true;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn decline(&mut self) {
+        self.answer = false;
         self.updated_at = Utc::now();
     }
 }
@@ -0,0 +1,339 @@
+use chrono::{DateTime, Datelike, Months, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::error::AppError;
+use super::ping::{CreatePingRequest, Ping};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    fn days_from_monday(self) -> i64 {
+        self as i64
+    }
+}
+
+/// When a series repeats, modelled on iCalendar RRULE. Occurrences fall at
+/// the time of day of `starts_at`, in UTC.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Validate)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Repeat every this many days, weeks or months
+    #[validate(range(min = 1, max = 52))]
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// Days of the week to repeat on; weekly only. Defaults to the day of
+    /// `starts_at`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub by_day: Vec<Weekday>,
+    /// First occurrence
+    pub starts_at: DateTime<Utc>,
+    /// No occurrences after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    /// Stop after this many occurrences
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Recurrence {
+    pub fn validate_rule(&self) -> Result<(), AppError> {
+        if !self.by_day.is_empty() && self.frequency != Frequency::Weekly {
+            return Err(AppError::BadRequest(
+                "by_day is only allowed for weekly recurrence".to_string(),
+            ));
+        }
+        if self.until.is_some_and(|until| until < self.starts_at) {
+            return Err(AppError::BadRequest(
+                "until must not be before starts_at".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Every occurrence in order, from `starts_at` until the rule runs out.
+    pub fn occurrences(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let count = self.count.map_or(usize::MAX, |c| c as usize);
+        (0..)
+            .map_while(|period| self.period(period))
+            .flatten()
+            .take(count)
+            .take_while(|at| self.until.is_none_or(|until| *at <= until))
+    }
+
+    /// The first occurrence after `after`, if the rule hasn't run out.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.occurrences().find(|at| *at > after)
+    }
+
+    /// Occurrences in the `period`th day, week or month of the rule; `None`
+    /// once the dates no longer fit.
+    fn period(&self, period: u32) -> Option<Vec<DateTime<Utc>>> {
+        let step = period.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => Some(vec![self.starts_at + TimeDelta::try_days(step.into())?]),
+            Frequency::Monthly => Some(vec![self.starts_at.checked_add_months(Months::new(step))?]),
+            Frequency::Weekly if self.by_day.is_empty() => {
+                Some(vec![self.starts_at + TimeDelta::try_weeks(step.into())?])
+            }
+            Frequency::Weekly => {
+                let monday = self.starts_at
+                    - TimeDelta::days(self.starts_at.weekday().num_days_from_monday().into());
+                let week = monday.checked_add_signed(TimeDelta::try_weeks(step.into())?)?;
+                let mut days = self.by_day.clone();
+                days.sort();
+                days.dedup();
+                days.into_iter()
+                    .map(|day| week.checked_add_signed(TimeDelta::days(day.days_from_monday())))
+                    .filter(|at| at.is_none_or(|at| at >= self.starts_at))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A ping that repeats on a schedule. The scheduler creates a fresh ping in
+/// the group for each occurrence.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PingSeries {
+    pub id: Uuid,
+    pub group: Uuid,
+    pub initiator: Uuid,
+    pub activity_type: String,
+    pub rough_timing: String,
+    pub vibe: Option<String>,
+    /// Members each ping goes to; empty means the whole group
+    #[serde(default)]
+    pub invitees: Vec<Uuid>,
+    pub recurrence: Recurrence,
+    pub paused: bool,
+    /// When the next ping goes out; `None` once the recurrence has run out
+    pub next_occurrence: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct CreatePingSeriesRequest {
+    pub initiator: Uuid,
+    #[validate(length(min = 1, max = 50))]
+    pub activity_type: String,
+    #[validate(length(min = 1, max = 50))]
+    pub rough_timing: String,
+    #[validate(length(max = 100))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vibe: Option<String>,
+    #[serde(default)]
+    pub invitees: Vec<Uuid>,
+    #[validate(nested)]
+    pub recurrence: Recurrence,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SeriesActionRequest {
+    pub user_id: Uuid,
+}
+
+impl PingSeries {
+    pub fn new(group: Uuid, request: CreatePingSeriesRequest, now: DateTime<Utc>) -> Self {
+        let next_occurrence = request.recurrence.occurrences().find(|at| *at >= now);
+
+        Self {
+            id: Uuid::new_v4(),
+            group,
+            initiator: request.initiator,
+            activity_type: request.activity_type,
+            rough_timing: request.rough_timing,
+            vibe: request.vibe,
+            invitees: request.invitees,
+            recurrence: request.recurrence,
+            paused: false,
+            next_occurrence,
+            created_at: now,
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.paused && self.next_occurrence.is_some_and(|at| at <= now)
+    }
+
+    /// Move on to the next occurrence after `now`. Occurrences missed while
+    /// the server was down or the series was paused are skipped rather than
+    /// sent all at once.
+    pub fn advance(&mut self, now: DateTime<Utc>) {
+        self.next_occurrence = self.recurrence.next_after(now);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self, now: DateTime<Utc>) {
+        self.paused = false;
+        if self.next_occurrence.is_some_and(|at| at < now) {
+            self.advance(now);
+        }
+    }
+
+    /// The ping for one occurrence, sent to whichever invitees are still in
+    /// the group; `None` if every invitee other than the initiator has left.
+    pub fn spawn_ping(&self, members: &[Uuid]) -> Option<Ping> {
+        let invitees: Vec<Uuid> = self
+            .invitees
+            .iter()
+            .copied()
+            .filter(|id| *id != self.initiator && members.contains(id))
+            .collect();
+        if invitees.is_empty() && !self.invitees.is_empty() {
+            return None;
+        }
+
+        Some(Ping {
+            series: Some(self.id),
+            ..Ping::new(CreatePingRequest {
+                initiator: self.initiator,
+                group: self.group,
+                template_id: None,
+                activity_type: self.activity_type.clone(),
+                rough_timing: self.rough_timing.clone(),
+                vibe: self.vibe.clone(),
+                capacity: None,
+                min_attendees: None,
+                force: true,
+                invitees,
+                send_at: None,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn recurrence(frequency: Frequency) -> Recurrence {
+        Recurrence {
+            frequency,
+            interval: 1,
+            by_day: vec![],
+            // A Thursday
+            starts_at: at("2099-01-01T19:00:00Z"),
+            until: None,
+            count: None,
+        }
+    }
+
+    #[test]
+    fn test_weekly_on_listed_days() {
+        let rule = Recurrence {
+            by_day: vec![Weekday::Tuesday, Weekday::Thursday, Weekday::Monday],
+            interval: 2,
+            count: Some(4),
+            ..recurrence(Frequency::Weekly)
+        };
+
+        // Monday and Tuesday of the first week are before starts_at
+        let occurrences: Vec<_> = rule.occurrences().collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                at("2099-01-01T19:00:00Z"),
+                at("2099-01-12T19:00:00Z"),
+                at("2099-01-13T19:00:00Z"),
+                at("2099-01-15T19:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_until_and_monthly() {
+        let rule = Recurrence {
+            until: Some(at("2099-03-01T19:00:00Z")),
+            ..recurrence(Frequency::Monthly)
+        };
+        assert_eq!(rule.occurrences().count(), 3);
+
+        let daily = Recurrence {
+            interval: 3,
+            ..recurrence(Frequency::Daily)
+        };
+        assert_eq!(
+            daily.next_after(at("2099-01-04T19:00:00Z")),
+            Some(at("2099-01-07T19:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_missed_occurrences_are_skipped() {
+        let mut series = PingSeries::new(
+            Uuid::new_v4(),
+            CreatePingSeriesRequest {
+                initiator: Uuid::new_v4(),
+                activity_type: "trivia".to_string(),
+                rough_timing: "thursday".to_string(),
+                vibe: None,
+                invitees: vec![],
+                recurrence: recurrence(Frequency::Weekly),
+            },
+            at("2098-12-31T00:00:00Z"),
+        );
+        assert_eq!(series.next_occurrence, Some(at("2099-01-01T19:00:00Z")));
+
+        series.pause();
+        assert!(!series.is_due(at("2099-01-01T19:00:00Z")));
+
+        series.resume(at("2099-01-20T00:00:00Z"));
+        assert_eq!(series.next_occurrence, Some(at("2099-01-22T19:00:00Z")));
+        assert!(series.is_due(at("2099-01-22T19:00:00Z")));
+    }
+
+    #[test]
+    fn test_spawn_skips_when_only_initiator_is_left() {
+        let initiator = Uuid::new_v4();
+        let friend = Uuid::new_v4();
+        let series = PingSeries::new(
+            Uuid::new_v4(),
+            CreatePingSeriesRequest {
+                initiator,
+                activity_type: "trivia".to_string(),
+                rough_timing: "thursday".to_string(),
+                vibe: None,
+                invitees: vec![initiator, friend],
+                recurrence: recurrence(Frequency::Weekly),
+            },
+            at("2098-12-31T00:00:00Z"),
+        );
+
+        let ping = series.spawn_ping(&[initiator, friend]).unwrap();
+        assert_eq!(ping.invitees, vec![friend]);
+        assert!(series.spawn_ping(&[initiator]).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::hangout::{AttendeeStatus, HangoutData};
+use super::venue::Venue;
+
+/// Who actually showed up to a finished hangout and for how long, worked
+/// out from the attendee status history rather than who confirmed.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HangoutSummary {
+    pub ping_id: Uuid,
+    pub venue: Option<Venue>,
+    /// Everyone who was confirmed, whether or not they came
+    pub confirmed_attendees: Vec<Uuid>,
+    /// Attendees who arrived, earliest first
+    pub attended: Vec<Attendance>,
+    /// Confirmed attendees who never arrived
+    pub no_shows: Vec<Uuid>,
+    /// First arrival
+    pub started_at: Option<DateTime<Utc>>,
+    /// Last departure, or when the hangout completed for anyone still there
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_minutes: i64,
+    /// Most attendees there at the same time
+    pub peak_attendance: usize,
+    pub peak_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Attendance {
+    pub user: Uuid,
+    pub arrived_at: DateTime<Utc>,
+    pub left_at: DateTime<Utc>,
+    /// Time actually there; stepping out and rejoining isn't counted
+    pub minutes_present: i64,
+}
+
+impl HangoutSummary {
+    /// Summarize a hangout that completed at `completed_at`; anyone who
+    /// never reported leaving is counted as there until then.
+    pub fn new(
+        ping_id: Uuid,
+        hangout: &HangoutData,
+        completed_at: DateTime<Utc>,
+    ) -> HangoutSummary {
+        let visits: Vec<(Uuid, Vec<Visit>)> = hangout
+            .confirmed_attendees
+            .iter()
+            .map(|user| (*user, Self::visits(hangout, *user, completed_at)))
+            .collect();
+
+        let mut attended: Vec<Attendance> = visits
+            .iter()
+            .filter_map(|(user, visits)| {
+                let first = visits.first()?;
+                let last = visits.last()?;
+                Some(Attendance {
+                    user: *user,
+                    arrived_at: first.arrived_at,
+                    left_at: last.left_at,
+                    minutes_present: visits
+                        .iter()
+                        .map(|v| (v.left_at - v.arrived_at).num_minutes())
+                        .sum(),
+                })
+            })
+            .collect();
+        attended.sort_by_key(|a| a.arrived_at);
+
+        let no_shows = visits
+            .iter()
+            .filter(|(_, visits)| visits.is_empty())
+            .map(|(user, _)| *user)
+            .collect();
+
+        let started_at = attended.iter().map(|a| a.arrived_at).min();
+        let ended_at = attended.iter().map(|a| a.left_at).max();
+        let duration_minutes = started_at
+            .zip(ended_at)
+            .map_or(0, |(start, end)| (end - start).num_minutes());
+
+        let all_visits: Vec<&Visit> = visits.iter().flat_map(|(_, v)| v).collect();
+        let (peak_attendance, peak_at) = Self::peak(&all_visits);
+
+        HangoutSummary {
+            ping_id,
+            venue: hangout.venue.clone(),
+            confirmed_attendees: hangout.confirmed_attendees.clone(),
+            attended,
+            no_shows,
+            started_at,
+            ended_at,
+            duration_minutes,
+            peak_attendance,
+            peak_at,
+        }
+    }
+
+    /// Each stretch of time the attendee spent at the hangout.
+    fn visits(hangout: &HangoutData, user: Uuid, completed_at: DateTime<Utc>) -> Vec<Visit> {
+        let mut visits = Vec::new();
+        let mut arrived_at = None;
+
+        for change in hangout.status_history.get(&user).into_iter().flatten() {
+            match (change.status, arrived_at) {
+                (AttendeeStatus::Arrived, None) => arrived_at = Some(change.changed_at),
+                (AttendeeStatus::Arrived, Some(_)) => {}
+                (_, Some(start)) => {
+                    visits.push(Visit {
+                        arrived_at: start,
+                        left_at: change.changed_at,
+                    });
+                    arrived_at = None;
+                }
+                (_, None) => {}
+            }
+        }
+        if let Some(start) = arrived_at {
+            visits.push(Visit {
+                arrived_at: start,
+                left_at: completed_at.max(start),
+            });
+        }
+
+        visits
+    }
+
+    /// Sweep arrivals and departures in time order; someone leaving at the
+    /// same moment another arrives doesn't count as overlapping.
+    fn peak(visits: &[&Visit]) -> (usize, Option<DateTime<Utc>>) {
+        let mut events: Vec<(DateTime<Utc>, i32)> = visits
+            .iter()
+            .flat_map(|v| [(v.arrived_at, 1), (v.left_at, -1)])
+            .collect();
+        events.sort();
+
+        let mut present = 0;
+        let mut peak = (0, None);
+        for (at, delta) in events {
+            present += delta;
+            if present as usize > peak.0 {
+                peak = (present as usize, Some(at));
+            }
+        }
+        peak
+    }
+}
+
+struct Visit {
+    arrived_at: DateTime<Utc>,
+    left_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{StatusChange, Timeline};
+    use chrono::TimeDelta;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        "2099-12-15T18:00:00Z".parse::<DateTime<Utc>>().unwrap() + TimeDelta::minutes(minutes)
+    }
+
+    fn set_status(hangout: &mut HangoutData, user: Uuid, status: AttendeeStatus, minutes: i64) {
+        hangout.update_attendee_status(
+            user,
+            StatusChange {
+                changed_at: at(minutes),
+                ..StatusChange::new(status)
+            },
+        );
+    }
+
+    fn hangout(attendees: &[Uuid]) -> HangoutData {
+        let timeline = Timeline {
+            start: at(0),
+            end: at(180),
+        };
+        HangoutData::new(attendees.to_vec(), timeline, None)
+    }
+
+    #[test]
+    fn test_attendance_from_history() {
+        let (alice, bob, carol) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut hangout = hangout(&[alice, bob, carol]);
+
+        set_status(&mut hangout, alice, AttendeeStatus::Arrived, 0);
+        set_status(&mut hangout, bob, AttendeeStatus::Delayed, 5);
+        set_status(&mut hangout, bob, AttendeeStatus::Arrived, 30);
+        set_status(&mut hangout, alice, AttendeeStatus::Left, 60);
+        set_status(&mut hangout, bob, AttendeeStatus::Left, 90);
+
+        let summary = HangoutSummary::new(Uuid::new_v4(), &hangout, at(120));
+
+        assert_eq!(summary.attended.len(), 2);
+        assert_eq!(summary.attended[0].user, alice);
+        assert_eq!(summary.attended[0].minutes_present, 60);
+        assert_eq!(summary.attended[1].user, bob);
+        assert_eq!(summary.attended[1].arrived_at, at(30));
+        assert_eq!(summary.no_shows, vec![carol]);
+        assert_eq!(summary.started_at, Some(at(0)));
+        assert_eq!(summary.ended_at, Some(at(90)));
+        assert_eq!(summary.duration_minutes, 90);
+        assert_eq!(summary.peak_attendance, 2);
+        assert_eq!(summary.peak_at, Some(at(30)));
+    }
+
+    #[test]
+    fn test_rejoin_and_still_there_at_completion() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut hangout = hangout(&[alice, bob]);
+
+        set_status(&mut hangout, alice, AttendeeStatus::Arrived, 0);
+        set_status(&mut hangout, alice, AttendeeStatus::Left, 30);
+        set_status(&mut hangout, bob, AttendeeStatus::Arrived, 30);
+        set_status(&mut hangout, alice, AttendeeStatus::Arrived, 60);
+
+        let summary = HangoutSummary::new(Uuid::new_v4(), &hangout, at(100));
+
+        let alice_attendance = summary.attended.iter().find(|a| a.user == alice).unwrap();
+        assert_eq!(alice_attendance.minutes_present, 70);
+        assert_eq!(alice_attendance.left_at, at(100));
+        assert_eq!(summary.ended_at, Some(at(100)));
+        assert_eq!(summary.peak_attendance, 2);
+        assert_eq!(summary.peak_at, Some(at(60)));
+    }
+
+    #[test]
+    fn test_nobody_arrived() {
+        let alice = Uuid::new_v4();
+        let summary = HangoutSummary::new(Uuid::new_v4(), &hangout(&[alice]), at(60));
+
+        assert!(summary.attended.is_empty());
+        assert_eq!(summary.no_shows, vec![alice]);
+        assert_eq!(summary.duration_minutes, 0);
+        assert_eq!(summary.peak_attendance, 0);
+        assert_eq!(summary.peak_at, None);
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::error::AppError;
+use super::ping::validate_min_attendees;
+
+/// Saved defaults for a ping the group sends often. Fields set on the
+/// create ping request win over the template's.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PingTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub activity_type: String,
+    pub rough_timing: Option<String>,
+    pub vibe: Option<String>,
+    pub capacity: Option<u32>,
+    pub min_attendees: Option<u32>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct CreatePingTemplateRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, max = 50))]
+    pub activity_type: String,
+    #[validate(length(min = 1, max = 50))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rough_timing: Option<String>,
+    #[validate(length(max = 100))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vibe: Option<String>,
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<u32>,
+    #[validate(range(min = 1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_attendees: Option<u32>,
+}
+
+impl CreatePingTemplateRequest {
+    pub fn validate_min_attendees(&self) -> Result<(), AppError> {
+        validate_min_attendees(self.min_attendees, self.capacity)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct DeletePingTemplateRequest {
+    pub user_id: Uuid,
+}
+
+impl PingTemplate {
+    pub fn new(request: CreatePingTemplateRequest) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: request.name,
+            activity_type: request.activity_type,
+            rough_timing: request.rough_timing,
+            vibe: request.vibe,
+            capacity: request.capacity,
+            min_attendees: request.min_attendees,
+            created_by: request.user_id,
+            created_at: Utc::now(),
+        }
+    }
+}
@@ -9,6 +9,19 @@ pub struct Location {
     pub lng: f64,
 }
 
+impl Location {
+    const EARTH_RADIUS_MILES: f64 = 3958.8;
+
+    /// Great-circle distance to another location, in miles.
+    pub fn distance_miles(&self, other: &Location) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlng = (other.lng - self.lng).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+        2.0 * Self::EARTH_RADIUS_MILES * a.sqrt().asin()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
 pub struct UserPreferences {
     #[serde(skip_serializing_if = "Option::is_none")]
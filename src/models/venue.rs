@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::error::AppError;
+use super::user::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VenueSource {
+    Favorite,
+    Discovery,
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityHint {
+    Small,
+    Medium,
+    Large,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Venue {
+    pub id: Uuid,
+    pub name: String,
+    pub location: Option<Location>,
+    pub area: Option<String>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub vibe_tags: Vec<String>,
+    pub capacity_hint: Option<CapacityHint>,
+    pub source: VenueSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VenueNote {
+    pub user: Uuid,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A venue the group has saved, plus the group's history with it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FavoriteVenue {
+    #[serde(flatten)]
+    pub venue: Venue,
+    pub times_visited: u32,
+    pub last_visited: Option<DateTime<Utc>>,
+    /// Average of `ratings`
+    pub group_rating: Option<f64>,
+    /// Latest rating (1-5) from each member who rated the venue
+    pub ratings: HashMap<Uuid, u8>,
+    pub group_notes: Vec<VenueNote>,
+}
+
+/// A scored venue produced by the matching phase, best first.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VenueOption {
+    pub venue: Venue,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct AddFavoriteRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[validate(length(max = 100))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area: Option<String>,
+    #[validate(length(max = 50))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub vibe_tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity_hint: Option<CapacityHint>,
+}
+
+/// Post-hangout feedback from an attendee about the venue
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct ReviewVenueRequest {
+    pub user_id: Uuid,
+    #[validate(range(min = 1, max = 5))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[validate(length(min = 1, max = 500))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Save the venue to group favorites if it isn't one already
+    #[serde(default)]
+    pub save_to_favorites: bool,
+}
+
+/// A "pick any place" venue entered by hand at confirmation time.
+#[derive(Debug, Clone, Deserialize, ToSchema, Validate)]
+pub struct CustomVenue {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[validate(length(max = 100))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area: Option<String>,
+}
+
+impl ReviewVenueRequest {
+    pub fn validate_review(&self) -> Result<(), AppError> {
+        if self.rating.is_none() && self.note.is_none() && !self.save_to_favorites {
+            return Err(AppError::BadRequest(
+                "Provide a rating, a note, or save_to_favorites".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Venue {
+    pub fn has_vibe(&self, vibe: &str) -> bool {
+        self.vibe_tags
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(vibe))
+    }
+
+    pub fn in_area(&self, area: &str) -> bool {
+        self.area
+            .as_deref()
+            .is_some_and(|a| a.eq_ignore_ascii_case(area))
+    }
+}
+
+impl From<CustomVenue> for Venue {
+    fn from(custom: CustomVenue) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: custom.name,
+            location: custom.location,
+            area: custom.area,
+            category: None,
+            vibe_tags: Vec::new(),
+            capacity_hint: None,
+            source: VenueSource::Custom,
+        }
+    }
+}
+
+impl FavoriteVenue {
+    pub fn new(request: AddFavoriteRequest) -> Self {
+        Self::from_venue(Venue {
+            id: Uuid::new_v4(),
+            name: request.name,
+            location: request.location,
+            area: request.area,
+            category: request.category,
+            vibe_tags: request.vibe_tags,
+            capacity_hint: request.capacity_hint,
+            source: VenueSource::Favorite,
+        })
+    }
+
+    /// Save a venue the group has been to, keeping its id so past hangouts
+    /// still point at it.
+    pub fn from_venue(venue: Venue) -> Self {
+        Self {
+            venue: Venue {
+                source: VenueSource::Favorite,
+                ..venue
+            },
+            times_visited: 0,
+            last_visited: None,
+            group_rating: None,
+            ratings: HashMap::new(),
+            group_notes: Vec::new(),
+        }
+    }
+
+    pub fn record_visit(&mut self, at: DateTime<Utc>) {
+        self.times_visited += 1;
+        self.last_visited = Some(self.last_visited.map_or(at, |last| last.max(at)));
+    }
+
+    pub fn rate(&mut self, user_id: Uuid, rating: u8) {
+        self.ratings.insert(user_id, rating);
+        let total: u32 = self.ratings.values().map(|&r| u32::from(r)).sum();
+        self.group_rating = Some(f64::from(total) / self.ratings.len() as f64);
+    }
+
+    pub fn add_note(&mut self, user_id: Uuid, text: String) {
+        self.group_notes.push(VenueNote {
+            user: user_id,
+            text,
+            created_at: Utc::now(),
+        });
+    }
+}
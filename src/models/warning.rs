@@ -0,0 +1,50 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::group::Group;
+use super::hangout::Timeline;
+use super::ping::Ping;
+
+/// Something worth pointing out to the user that doesn't stop the request
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// The user is confirmed for another hangout at an overlapping time
+    HangoutConflict(HangoutConflict),
+    /// Nobody else is in the group yet; share the invite code to fix that
+    SoloGroup { group_id: Uuid, invite_code: String },
+}
+
+impl Warning {
+    /// Warn when `group` has nobody but the user in it.
+    pub fn solo_group(group: &Group) -> Option<Warning> {
+        (group.members.len() == 1).then(|| Warning::SoloGroup {
+            group_id: group.id,
+            invite_code: group.invite_code.clone(),
+        })
+    }
+}
+
+/// Two confirmed or active hangouts a user is part of that overlap in time
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HangoutConflict {
+    pub user: Uuid,
+    pub ping_id: Uuid,
+    pub conflicting_ping_id: Uuid,
+    pub overlap: Timeline,
+}
+
+/// A ping along with any warnings raised while handling the request
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PingWithWarnings {
+    #[serde(flatten)]
+    pub ping: Ping,
+    pub warnings: Vec<Warning>,
+}
+
+impl PingWithWarnings {
+    pub fn new(ping: Ping, warnings: Vec<Warning>) -> Self {
+        Self { ping, warnings }
+    }
+}
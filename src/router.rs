@@ -4,9 +4,10 @@ use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::handlers::{groups, pings, responses, users};
+use crate::handlers::{groups, meta, pings, responses, series, users};
 use crate::models;
 use crate::state::AppState;
+use crate::transitions;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -19,7 +20,9 @@ use crate::state::AppState;
         (name = "Users", description = "User management"),
         (name = "Groups", description = "Group management"),
         (name = "Pings", description = "Ping lifecycle"),
-        (name = "Responses", description = "Ping responses")
+        (name = "Responses", description = "Ping responses"),
+        (name = "Series", description = "Recurring pings"),
+        (name = "Meta", description = "API metadata")
     ),
     components(schemas(
         models::ApiError,
@@ -33,11 +36,37 @@ use crate::state::AppState;
         models::JoinGroupRequest,
         models::LeaveGroupRequest,
         models::RegenerateInviteRequest,
+        models::GroupSettings,
+        models::UpdateGroupSettingsRequest,
+        models::PingTemplate,
+        models::CreatePingTemplateRequest,
+        models::DeletePingTemplateRequest,
+        models::Venue,
+        models::VenueSource,
+        models::CapacityHint,
+        models::FavoriteVenue,
+        models::VenueOption,
+        models::AddFavoriteRequest,
+        models::CustomVenue,
+        models::VenueNote,
+        models::ReviewVenueRequest,
         models::Ping,
         models::PingLifecycle,
+        models::LifecycleState,
+        models::Actor,
         models::CreatePingRequest,
+        models::UpdatePingRequest,
         models::CancelPingRequest,
+        models::RetryPingRequest,
         models::TriggerMatchRequest,
+        models::ActivatePingRequest,
+        models::CompletePingRequest,
+        models::PingSeries,
+        models::Recurrence,
+        models::Frequency,
+        models::Weekday,
+        models::CreatePingSeriesRequest,
+        models::SeriesActionRequest,
         models::Response,
         models::Availability,
         models::ResponsePreferences,
@@ -45,11 +74,26 @@ use crate::state::AppState;
         models::UpdateResponseRequest,
         models::HangoutData,
         models::AttendeeStatus,
+        models::StatusChange,
+        models::Eta,
         models::Timeline,
         models::ConfirmHangoutRequest,
+        models::FlagVenueRequest,
         models::UpdateAttendeeStatusRequest,
+        models::WithdrawRequest,
+        models::JoinRequest,
+        models::LateJoinRequest,
+        models::DecideJoinRequest,
+        models::HangoutSummary,
+        models::Attendance,
+        models::Warning,
+        models::HangoutConflict,
+        models::PingWithWarnings,
         models::MatchResults,
         models::TimeOverlap,
+        transitions::PingEventKind,
+        transitions::Transition,
+        transitions::AttendeeTransition,
     ))
 )]
 struct ApiDoc;
@@ -64,6 +108,7 @@ pub fn create_router(state: AppState) -> Router {
             users::delete_user
         ))
         .routes(routes!(users::list_user_groups))
+        .routes(routes!(users::list_user_conflicts))
         // Groups
         .routes(routes!(groups::create_group))
         .routes(routes!(groups::get_group))
@@ -71,19 +116,40 @@ pub fn create_router(state: AppState) -> Router {
         .routes(routes!(groups::leave_group))
         .routes(routes!(groups::regenerate_invite_code))
         .routes(routes!(groups::list_group_pings))
+        .routes(routes!(groups::update_group_settings))
+        .routes(routes!(groups::add_favorite, groups::list_favorites))
+        .routes(routes!(groups::add_template, groups::list_templates))
+        .routes(routes!(groups::delete_template))
         // Pings
         .routes(routes!(pings::create_ping))
-        .routes(routes!(pings::get_ping))
+        .routes(routes!(pings::get_ping, pings::update_ping))
         .routes(routes!(pings::cancel_ping))
+        .routes(routes!(pings::retry_ping))
         .routes(routes!(pings::trigger_match))
         .routes(routes!(pings::get_match_results))
         .routes(routes!(pings::confirm_hangout))
+        .routes(routes!(pings::flag_venue_unavailable))
+        .routes(routes!(pings::withdraw))
+        .routes(routes!(pings::request_join))
+        .routes(routes!(pings::approve_join_request))
+        .routes(routes!(pings::deny_join_request))
         .routes(routes!(pings::activate_ping))
         .routes(routes!(pings::complete_ping))
+        .routes(routes!(pings::get_summary))
+        .routes(routes!(pings::review_venue))
         .routes(routes!(pings::update_attendee_status))
         // Responses
         .routes(routes!(responses::create_response))
         .routes(routes!(responses::update_response))
+        // Series
+        .routes(routes!(series::create_series, series::list_group_series))
+        .routes(routes!(series::get_series, series::delete_series))
+        .routes(routes!(series::pause_series))
+        .routes(routes!(series::resume_series))
+        .routes(routes!(series::list_series_pings))
+        // Meta
+        .routes(routes!(meta::list_transitions))
+        .routes(routes!(meta::list_attendee_transitions))
         .with_state(state)
         .split_for_parts();
 
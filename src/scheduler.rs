@@ -0,0 +1,296 @@
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::task::JoinHandle;
+
+use crate::models::{Actor, Ping, PingLifecycle, PingSeries};
+use crate::state::AppState;
+use crate::state_machine::StateMachine;
+use crate::transitions::PingEvent;
+
+/// How often the scheduler looks for time-driven transitions
+pub const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long past `timeline.end` an active hangout is left open for
+/// stragglers before it is completed automatically
+pub const COMPLETION_GRACE: TimeDelta = TimeDelta::hours(1);
+
+/// Background task that drives the time-based transitions in the ping
+/// lifecycle. Manual endpoints still work for doing these early.
+pub struct Scheduler;
+
+impl Scheduler {
+    pub fn spawn(state: AppState) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK_INTERVAL);
+            loop {
+                interval.tick().await;
+                Self::run_due(&state, Utc::now());
+            }
+        })
+    }
+
+    /// Apply every transition that is due at `now`.
+    pub fn run_due(state: &AppState, now: DateTime<Utc>) {
+        for series in state.series.filter(|s| s.is_due(now)) {
+            let mut due = false;
+            state.series.update(&series.id, |s| {
+                due = s.is_due(now);
+                if due {
+                    s.advance(now);
+                }
+            });
+            if due {
+                Self::spawn_series_ping(state, &series);
+            }
+        }
+
+        for ping in state.pings.filter(|p| Self::send_reached(p, now)) {
+            state.pings.update(&ping.id, |p| {
+                if Self::send_reached(p, now) {
+                    match StateMachine::apply(p, PingEvent::SendTimeReached, Actor::System) {
+                        Ok(()) => tracing::info!(ping_id = %p.id, "Scheduled ping sent"),
+                        Err(e) => tracing::warn!(ping_id = %p.id, "{e}"),
+                    }
+                }
+            });
+        }
+
+        for ping in state.pings.filter(|p| Self::start_reached(p, now)) {
+            // Re-check under the write lock in case the ping moved on
+            state.pings.update(&ping.id, |p| {
+                if Self::start_reached(p, now) {
+                    match StateMachine::apply(p, PingEvent::StartTimeReached, Actor::System) {
+                        Ok(()) => tracing::info!(ping_id = %p.id, "Hangout start time reached"),
+                        Err(e) => tracing::warn!(ping_id = %p.id, "{e}"),
+                    }
+                }
+            });
+        }
+
+        for ping in state.pings.filter(|p| Self::grace_expired(p, now)) {
+            let mut completed = false;
+            let updated = state.pings.update(&ping.id, |p| {
+                if Self::grace_expired(p, now) {
+                    match StateMachine::apply(p, PingEvent::TimedOut, Actor::System) {
+                        Ok(()) => {
+                            completed = true;
+                            tracing::info!(ping_id = %p.id, "Hangout grace period expired");
+                        }
+                        Err(e) => tracing::warn!(ping_id = %p.id, "{e}"),
+                    }
+                }
+            });
+            if let Some(updated) = updated.filter(|_| completed) {
+                state.record_favorite_visit(&updated);
+            }
+        }
+    }
+
+    /// Create the ping for a series occurrence, skipping it if the initiator
+    /// or all of the invitees have since left the group.
+    fn spawn_series_ping(state: &AppState, series: &PingSeries) {
+        let ping = state
+            .groups
+            .get(&series.group)
+            .filter(|g| g.is_member(series.initiator))
+            .and_then(|g| series.spawn_ping(&g.members));
+        let Some(ping) = ping else {
+            tracing::warn!(series_id = %series.id, "Nobody left to ping; occurrence skipped");
+            return;
+        };
+
+        tracing::info!(series_id = %series.id, ping_id = %ping.id, "Series ping created");
+        state.pings.insert(ping.id, ping);
+    }
+
+    fn send_reached(ping: &Ping, now: DateTime<Utc>) -> bool {
+        ping.lifecycle.send_at().is_some_and(|at| at <= now)
+    }
+
+    fn start_reached(ping: &Ping, now: DateTime<Utc>) -> bool {
+        matches!(
+            &ping.lifecycle,
+            PingLifecycle::VenueConfirmed { hangout, .. } if hangout.timeline.start <= now
+        )
+    }
+
+    fn grace_expired(ping: &Ping, now: DateTime<Utc>) -> bool {
+        matches!(
+            &ping.lifecycle,
+            PingLifecycle::ActiveHangout { hangout, .. }
+                if hangout.timeline.end + COMPLETION_GRACE <= now
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        CreateGroupRequest, CreatePingRequest, CreatePingSeriesRequest, Frequency, Group,
+        HangoutData, MatchResults, Recurrence, Timeline,
+    };
+    use chrono::Duration;
+    use uuid::Uuid;
+
+    fn insert_active_ping(state: &AppState, end: DateTime<Utc>) -> Uuid {
+        let id = insert_confirmed_ping(state, end - Duration::hours(3));
+        state
+            .pings
+            .try_update(&id, |p| {
+                StateMachine::apply(p, PingEvent::StartTimeReached, Actor::System)
+            })
+            .unwrap();
+        id
+    }
+
+    fn new_ping(send_at: Option<DateTime<Utc>>) -> Ping {
+        Ping::new(CreatePingRequest {
+            initiator: Uuid::new_v4(),
+            group: Uuid::new_v4(),
+            template_id: None,
+            activity_type: "drinks".to_string(),
+            rough_timing: "tonight".to_string(),
+            vibe: None,
+            capacity: None,
+            min_attendees: None,
+            force: false,
+            invitees: vec![],
+            send_at,
+        })
+    }
+
+    fn insert_confirmed_ping(state: &AppState, start: DateTime<Utc>) -> Uuid {
+        let mut ping = new_ping(None);
+        let timeline = Timeline {
+            start,
+            end: start + Duration::hours(3),
+        };
+        ping.lifecycle = PingLifecycle::VenueConfirmed {
+            entered_at: Utc::now(),
+            actor: Actor::User {
+                user_id: ping.initiator,
+            },
+            responses: vec![],
+            match_results: MatchResults {
+                ping_id: ping.id,
+                overlap: None,
+                has_match: true,
+                venue_options: vec![],
+            },
+            hangout: HangoutData::new(vec![Uuid::new_v4()], timeline, None),
+        };
+        state.pings.insert(ping.id, ping.clone());
+        ping.id
+    }
+
+    #[test]
+    fn test_sends_scheduled_ping() {
+        let state = AppState::new();
+        let now = Utc::now();
+        let due = new_ping(Some(now - Duration::minutes(1)));
+        let later = new_ping(Some(now + Duration::hours(1)));
+        state.pings.insert(due.id, due.clone());
+        state.pings.insert(later.id, later.clone());
+
+        Scheduler::run_due(&state, now);
+
+        let sent = state.pings.get(&due.id).unwrap();
+        assert_eq!(sent.lifecycle.state_name(), "ping_sent");
+        assert_eq!(sent.lifecycle.actor(), Actor::System);
+        assert_eq!(
+            state.pings.get(&later.id).unwrap().lifecycle.state_name(),
+            "scheduled"
+        );
+    }
+
+    #[test]
+    fn test_spawns_series_ping() {
+        let state = AppState::new();
+        let now = Utc::now();
+        let initiator = Uuid::new_v4();
+        let mut group = Group::new(CreateGroupRequest {
+            name: "Trivia".to_string(),
+            creator_id: initiator,
+        });
+        let left = Uuid::new_v4();
+        let member = Uuid::new_v4();
+        group.add_member(member);
+        state.groups.insert(group.id, group.clone());
+
+        let series = PingSeries::new(
+            group.id,
+            CreatePingSeriesRequest {
+                initiator,
+                activity_type: "trivia".to_string(),
+                rough_timing: "thursday".to_string(),
+                vibe: None,
+                invitees: vec![member, left],
+                recurrence: Recurrence {
+                    frequency: Frequency::Daily,
+                    interval: 1,
+                    by_day: vec![],
+                    starts_at: now - Duration::days(3) - Duration::minutes(1),
+                    until: None,
+                    count: None,
+                },
+            },
+            now - Duration::days(3),
+        );
+        state.series.insert(series.id, series.clone());
+
+        Scheduler::run_due(&state, now);
+        Scheduler::run_due(&state, now);
+
+        // One ping for the missed occurrences, then nothing until tomorrow
+        let pings = state.get_series_pings(series.id);
+        assert_eq!(pings.len(), 1);
+        assert_eq!(pings[0].lifecycle.state_name(), "ping_sent");
+        assert_eq!(pings[0].invitees, vec![member]);
+        let next = state
+            .series
+            .get(&series.id)
+            .unwrap()
+            .next_occurrence
+            .unwrap();
+        assert!(next > now && next <= now + Duration::days(1));
+    }
+
+    #[test]
+    fn test_activates_when_start_reached() {
+        let state = AppState::new();
+        let now = Utc::now();
+        let due = insert_confirmed_ping(&state, now - Duration::minutes(1));
+        let upcoming = insert_confirmed_ping(&state, now + Duration::hours(1));
+
+        Scheduler::run_due(&state, now);
+
+        let activated = state.pings.get(&due).unwrap();
+        assert_eq!(activated.lifecycle.state_name(), "active_hangout");
+        assert_eq!(activated.lifecycle.actor(), Actor::System);
+        assert_eq!(
+            state.pings.get(&upcoming).unwrap().lifecycle.state_name(),
+            "venue_confirmed"
+        );
+    }
+
+    #[test]
+    fn test_completes_after_grace_period() {
+        let state = AppState::new();
+        let now = Utc::now();
+        let expired = insert_active_ping(&state, now - COMPLETION_GRACE - Duration::minutes(1));
+        let lingering = insert_active_ping(&state, now - Duration::minutes(1));
+
+        Scheduler::run_due(&state, now);
+
+        assert_eq!(
+            state.pings.get(&expired).unwrap().lifecycle.state_name(),
+            "complete"
+        );
+        assert_eq!(
+            state.pings.get(&lingering).unwrap().lifecycle.state_name(),
+            "active_hangout"
+        );
+    }
+}
@@ -5,7 +5,11 @@ use std::{
 
 use uuid::Uuid;
 
-use crate::models::{Group, Ping, User};
+use crate::discovery::VenueProvider;
+use crate::matching::MatchContext;
+use crate::models::{
+    Group, HangoutConflict, Ping, PingLifecycle, PingSeries, Timeline, User, Warning,
+};
 
 #[derive(Debug, Default)]
 pub struct InMemoryStore<T> {
@@ -40,6 +44,57 @@ impl<T: Clone> InMemoryStore<T> {
         }
     }
 
+    /// Like `update`, but the change may fail; the error is passed back,
+    /// the stored item is left untouched, and `None` still means the item
+    /// doesn't exist.
+    pub fn try_update<F, E>(&self, id: &Uuid, f: F) -> Result<Option<T>, E>
+    where
+        F: FnOnce(&mut T) -> Result<(), E>,
+    {
+        let mut data = self.data.write().unwrap();
+        let Some(mut item) = data.get(id).cloned() else {
+            return Ok(None);
+        };
+        f(&mut item)?;
+        data.insert(*id, item.clone());
+        Ok(Some(item))
+    }
+
+    /// Like `try_update`, then `check` the changed item against every other
+    /// item before storing it; all under one lock.
+    pub fn try_update_checked<F, C, E>(&self, id: &Uuid, f: F, check: C) -> Result<Option<T>, E>
+    where
+        F: FnOnce(&mut T) -> Result<(), E>,
+        C: Fn(&T, &T) -> Result<(), E>,
+    {
+        let mut data = self.data.write().unwrap();
+        let Some(mut item) = data.get(id).cloned() else {
+            return Ok(None);
+        };
+        f(&mut item)?;
+        for (other_id, other) in data.iter() {
+            if other_id != id {
+                check(&item, other)?;
+            }
+        }
+        data.insert(*id, item.clone());
+        Ok(Some(item))
+    }
+
+    /// Insert the item unless another item matches `conflicts`; the check
+    /// and the insert happen under one lock. Returns the conflicting item.
+    pub fn try_insert<F>(&self, id: Uuid, item: T, conflicts: F) -> Result<(), T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut data = self.data.write().unwrap();
+        if let Some(existing) = data.values().find(|other| conflicts(other)) {
+            return Err(existing.clone());
+        }
+        data.insert(id, item);
+        Ok(())
+    }
+
     pub fn remove(&self, id: &Uuid) -> Option<T> {
         self.data.write().unwrap().remove(id)
     }
@@ -79,6 +134,8 @@ pub struct AppState {
     pub users: Arc<InMemoryStore<User>>,
     pub groups: Arc<InMemoryStore<Group>>,
     pub pings: Arc<InMemoryStore<Ping>>,
+    pub series: Arc<InMemoryStore<PingSeries>>,
+    pub venue_provider: Option<Arc<dyn VenueProvider>>,
 }
 
 impl Default for AppState {
@@ -93,9 +150,16 @@ impl AppState {
             users: Arc::new(InMemoryStore::new()),
             groups: Arc::new(InMemoryStore::new()),
             pings: Arc::new(InMemoryStore::new()),
+            series: Arc::new(InMemoryStore::new()),
+            venue_provider: None,
         }
     }
 
+    pub fn with_venue_provider(mut self, provider: Arc<dyn VenueProvider>) -> Self {
+        self.venue_provider = Some(provider);
+        self
+    }
+
     pub fn get_user_groups(&self, user_id: Uuid) -> Vec<Group> {
         self.groups.filter(|g| g.is_member(user_id))
     }
@@ -107,4 +171,147 @@ impl AppState {
     pub fn get_group_pings(&self, group_id: Uuid) -> Vec<Ping> {
         self.pings.filter(|p| p.group == group_id)
     }
+
+    /// Pings a series has created so far, oldest first
+    pub fn get_series_pings(&self, series_id: Uuid) -> Vec<Ping> {
+        let mut pings = self.pings.filter(|p| p.series == Some(series_id));
+        pings.sort_by_key(|p| p.created_at);
+        pings
+    }
+
+    /// Count a completed hangout as a visit if its venue is a group favorite.
+    pub fn record_favorite_visit(&self, ping: &Ping) {
+        let Some(hangout) = ping.lifecycle.hangout() else {
+            return;
+        };
+        let Some(venue) = &hangout.venue else {
+            return;
+        };
+
+        self.groups.update(&ping.group, |g| {
+            if let Some(favorite) = g.find_favorite_mut(venue.id) {
+                favorite.record_visit(hangout.visited_at());
+            }
+        });
+    }
+
+    /// Confirmed or active hangouts `user_id` is part of, as the initiator
+    /// or an attendee.
+    pub fn user_hangouts(&self, user_id: Uuid) -> Vec<Ping> {
+        self.pings.filter(|p| {
+            matches!(
+                p.lifecycle,
+                PingLifecycle::VenueConfirmed { .. } | PingLifecycle::ActiveHangout { .. }
+            ) && (p.initiator == user_id
+                || p.lifecycle
+                    .hangout()
+                    .is_some_and(|h| h.is_attendee(user_id)))
+        })
+    }
+
+    /// The user's other hangouts that overlap `timeline` on ping `ping_id`.
+    pub fn hangout_conflicts(
+        &self,
+        user_id: Uuid,
+        ping_id: Uuid,
+        timeline: &Timeline,
+    ) -> Vec<HangoutConflict> {
+        let mut conflicts: Vec<HangoutConflict> = self
+            .user_hangouts(user_id)
+            .into_iter()
+            .filter(|p| p.id != ping_id)
+            .filter_map(|p| {
+                let overlap = p.lifecycle.hangout()?.timeline.overlap(timeline)?;
+                Some(HangoutConflict {
+                    user: user_id,
+                    ping_id,
+                    conflicting_ping_id: p.id,
+                    overlap,
+                })
+            })
+            .collect();
+        conflicts.sort_by_key(|c| c.overlap.start);
+        conflicts
+    }
+
+    /// Every pair of the user's hangouts that overlap, each pair once.
+    pub fn user_conflicts(&self, user_id: Uuid) -> Vec<HangoutConflict> {
+        let mut hangouts: Vec<(Uuid, Timeline)> = self
+            .user_hangouts(user_id)
+            .into_iter()
+            .filter_map(|p| Some((p.id, p.lifecycle.hangout()?.timeline.clone())))
+            .collect();
+        hangouts.sort_by_key(|(id, timeline)| (timeline.start, *id));
+
+        let mut conflicts = Vec::new();
+        for (i, (ping_id, timeline)) in hangouts.iter().enumerate() {
+            for (other_id, other) in &hangouts[i + 1..] {
+                if let Some(overlap) = timeline.overlap(other) {
+                    conflicts.push(HangoutConflict {
+                        user: user_id,
+                        ping_id: *ping_id,
+                        conflicting_ping_id: *other_id,
+                        overlap,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Conflict warnings for everyone in `users` taking part in `ping`.
+    pub fn conflict_warnings(&self, ping: &Ping, users: &[Uuid]) -> Vec<Warning> {
+        let Some(hangout) = ping.lifecycle.hangout() else {
+            return Vec::new();
+        };
+        users
+            .iter()
+            .flat_map(|user| self.hangout_conflicts(*user, ping.id, &hangout.timeline))
+            .map(Warning::HangoutConflict)
+            .collect()
+    }
+
+    pub fn match_context(&self, ping: &Ping) -> MatchContext {
+        let favorites = self
+            .groups
+            .get(&ping.group)
+            .map(|g| g.favorites)
+            .unwrap_or_default();
+
+        let attendee_locations = ping
+            .positive_responses()
+            .iter()
+            .filter_map(|r| {
+                let user = self.users.get(&r.user)?;
+                let location = user.preferences?.home_location?;
+                Some((r.user, location))
+            })
+            .collect();
+
+        MatchContext {
+            favorites,
+            attendee_locations,
+            provider: self.venue_provider.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_update_leaves_item_untouched() {
+        let store = InMemoryStore::new();
+        let id = Uuid::new_v4();
+        store.insert(id, vec![1]);
+
+        let result = store.try_update(&id, |items| {
+            items.push(2);
+            Err("guard failed")
+        });
+
+        assert_eq!(result, Err("guard failed"));
+        assert_eq!(store.get(&id), Some(vec![1]));
+    }
 }
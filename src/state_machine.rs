@@ -1,12 +1,22 @@
+use chrono::Utc;
 use uuid::Uuid;
 
-use crate::models::{AppError, HangoutData, MatchResults, Ping, PingLifecycle, Timeline};
+use crate::models::ping::validate_min_attendees;
+use crate::models::{
+    Actor, AppError, AttendeeStatus, HangoutData, MatchResults, Ping, PingLifecycle, Response,
+    StatusChange, Timeline, UpdatePingRequest, Venue,
+};
+use crate::transitions::{self, PingEvent, PingEventKind, TransitionError};
 
 pub struct StateMachine;
 
 impl StateMachine {
+    fn allows(ping: &Ping, event: PingEventKind) -> bool {
+        transitions::allows(ping.lifecycle.state(), event)
+    }
+
     pub fn can_add_response(ping: &Ping) -> Result<(), AppError> {
-        if !ping.lifecycle.can_add_response() {
+        if !Self::allows(ping, PingEventKind::ResponseReceived) {
             return Err(AppError::Conflict(format!(
                 "Cannot add response when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -22,7 +32,7 @@ impl StateMachine {
             ));
         }
 
-        if !ping.lifecycle.can_trigger_match() {
+        if !Self::allows(ping, PingEventKind::MatchFound) {
             return Err(AppError::Conflict(format!(
                 "Cannot trigger match when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -33,7 +43,7 @@ impl StateMachine {
     }
 
     pub fn can_confirm(ping: &Ping) -> Result<(), AppError> {
-        if !ping.lifecycle.can_confirm() {
+        if !Self::allows(ping, PingEventKind::VenueChosen) {
             return Err(AppError::Conflict(format!(
                 "Cannot confirm when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -42,8 +52,105 @@ impl StateMachine {
         Ok(())
     }
 
-    pub fn can_activate(ping: &Ping) -> Result<(), AppError> {
-        if !ping.lifecycle.can_activate() {
+    pub fn can_flag_venue(ping: &Ping) -> Result<(), AppError> {
+        let PingLifecycle::VenueConfirmed { hangout, .. } = &ping.lifecycle else {
+            return Err(AppError::Conflict(format!(
+                "Cannot flag venue when ping is in {} state",
+                ping.lifecycle.state_name()
+            )));
+        };
+
+        if hangout.venue.is_none() {
+            return Err(AppError::Conflict(
+                "Hangout has no venue to flag".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn can_withdraw(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        let PingLifecycle::VenueConfirmed { hangout, .. } = &ping.lifecycle else {
+            return Err(AppError::Conflict(format!(
+                "Cannot withdraw when ping is in {} state",
+                ping.lifecycle.state_name()
+            )));
+        };
+
+        if !hangout.is_attendee(user_id) {
+            return Err(AppError::NotFound("Attendee".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn can_request_join(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        let hangout = match &ping.lifecycle {
+            PingLifecycle::VenueConfirmed { hangout, .. }
+            | PingLifecycle::ActiveHangout { hangout, .. } => hangout,
+            _ => {
+                return Err(AppError::Conflict(format!(
+                    "Cannot join when ping is in {} state",
+                    ping.lifecycle.state_name()
+                )));
+            }
+        };
+
+        if !ping.is_invited(user_id) {
+            return Err(AppError::Forbidden(
+                "User was not invited to this ping".to_string(),
+            ));
+        }
+        if hangout.is_attendee(user_id) {
+            return Err(AppError::Conflict("User is already attending".to_string()));
+        }
+        if ping.is_waitlisted(user_id) {
+            return Err(AppError::Conflict(
+                "User is already on the waitlist".to_string(),
+            ));
+        }
+        if hangout.has_join_request(user_id) {
+            return Err(AppError::Conflict(
+                "User has already requested to join".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn can_decide_join_request(
+        ping: &Ping,
+        acting_user: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        if ping.initiator != acting_user {
+            return Err(AppError::Forbidden(
+                "Only initiator can decide join requests".to_string(),
+            ));
+        }
+
+        let hangout = match &ping.lifecycle {
+            PingLifecycle::VenueConfirmed { hangout, .. }
+            | PingLifecycle::ActiveHangout { hangout, .. } => hangout,
+            _ => {
+                return Err(AppError::Conflict(format!(
+                    "Cannot decide join requests when ping is in {} state",
+                    ping.lifecycle.state_name()
+                )));
+            }
+        };
+
+        if !hangout.has_join_request(user_id) {
+            return Err(AppError::NotFound("Join request".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn can_activate(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        if !Self::can_run_hangout(ping, user_id) {
+            return Err(AppError::Forbidden(
+                "Only initiator can activate ping".to_string(),
+            ));
+        }
+
+        if !Self::allows(ping, PingEventKind::InitiatorStarts) {
             return Err(AppError::Conflict(format!(
                 "Cannot activate when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -52,8 +159,14 @@ impl StateMachine {
         Ok(())
     }
 
-    pub fn can_complete(ping: &Ping) -> Result<(), AppError> {
-        if !ping.lifecycle.can_complete() {
+    pub fn can_complete(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        if !Self::can_run_hangout(ping, user_id) {
+            return Err(AppError::Forbidden(
+                "Only initiator can complete ping".to_string(),
+            ));
+        }
+
+        if !Self::allows(ping, PingEventKind::InitiatorEnds) {
             return Err(AppError::Conflict(format!(
                 "Cannot complete when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -62,6 +175,59 @@ impl StateMachine {
         Ok(())
     }
 
+    /// Attendees only report their own status.
+    pub fn can_update_attendee_status(acting_user: Uuid, attendee: Uuid) -> Result<(), AppError> {
+        if acting_user != attendee {
+            return Err(AppError::Forbidden(
+                "Attendees can only update their own status".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The initiator starts and ends the hangout. Once the initiator is
+    /// absent (not a confirmed attendee, or has left) any attendee still
+    /// there stands in, so the hangout can't get stuck.
+    fn can_run_hangout(ping: &Ping, user_id: Uuid) -> bool {
+        if ping.initiator == user_id {
+            return true;
+        }
+
+        let Some(hangout) = ping.lifecycle.hangout() else {
+            return false;
+        };
+        let has_left = |id: Uuid| hangout.attendee_statuses.get(&id) == Some(&AttendeeStatus::Left);
+        let initiator_absent = !hangout.is_attendee(ping.initiator) || has_left(ping.initiator);
+
+        initiator_absent && hangout.is_attendee(user_id) && !has_left(user_id)
+    }
+
+    pub fn can_review_venue(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        let PingLifecycle::Complete { hangout, .. } = &ping.lifecycle else {
+            return Err(AppError::Conflict(format!(
+                "Cannot review venue when ping is in {} state",
+                ping.lifecycle.state_name()
+            )));
+        };
+
+        if ping.initiator != user_id && !hangout.is_attendee(user_id) {
+            return Err(AppError::Forbidden(
+                "Only attendees can review the venue".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn can_view_summary(ping: &Ping) -> Result<(), AppError> {
+        if !matches!(ping.lifecycle, PingLifecycle::Complete { .. }) {
+            return Err(AppError::Conflict(
+                "Summary is only available once the hangout is complete".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     pub fn can_cancel(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
         if ping.initiator != user_id {
             return Err(AppError::Forbidden(
@@ -69,7 +235,7 @@ impl StateMachine {
             ));
         }
 
-        if !ping.lifecycle.can_cancel() {
+        if !Self::allows(ping, PingEventKind::InitiatorCancels) {
             return Err(AppError::Conflict(format!(
                 "Cannot cancel when ping is in {} state",
                 ping.lifecycle.state_name()
@@ -79,55 +245,303 @@ impl StateMachine {
         Ok(())
     }
 
-    pub fn transition_to_matching(ping: &mut Ping, match_results: MatchResults) {
-        if match_results.has_match {
-            if let PingLifecycle::Gathering { responses } = &ping.lifecycle {
-                ping.lifecycle = PingLifecycle::Matching {
-                    responses: responses.clone(),
-                    match_results,
-                };
-            }
-        } else if let PingLifecycle::Gathering { responses } = &ping.lifecycle {
-            ping.lifecycle = PingLifecycle::NoMatch {
-                responses: responses.clone(),
-            };
+    /// Details can be fixed until matching starts; the send time only
+    /// while the ping is still scheduled.
+    pub fn can_edit(ping: &Ping, request: &UpdatePingRequest) -> Result<(), AppError> {
+        if ping.initiator != request.user_id {
+            return Err(AppError::Forbidden(
+                "Only initiator can edit ping".to_string(),
+            ));
+        }
+
+        let scheduled = matches!(ping.lifecycle, PingLifecycle::Scheduled { .. });
+        if !scheduled
+            && !matches!(
+                ping.lifecycle,
+                PingLifecycle::PingSent { .. } | PingLifecycle::Gathering { .. }
+            )
+        {
+            return Err(AppError::Conflict(format!(
+                "Cannot edit when ping is in {} state",
+                ping.lifecycle.state_name()
+            )));
+        }
+
+        if request.send_at.is_some() && !scheduled {
+            return Err(AppError::Conflict(
+                "send_at can only be changed while the ping is scheduled".to_string(),
+            ));
         }
+
+        let confirmed = ping.positive_responses().len();
+        if request.capacity.is_some_and(|c| (c as usize) < confirmed) {
+            return Err(AppError::Conflict(format!(
+                "Capacity can't be lower than the {confirmed} people who already said yes"
+            )));
+        }
+        validate_min_attendees(ping.min_attendees, request.capacity)?;
+
+        Ok(())
     }
 
-    pub fn transition_to_venue_confirmed(ping: &mut Ping, hangout: HangoutData) {
-        if let PingLifecycle::Matching { responses, .. } = &ping.lifecycle {
-            ping.lifecycle = PingLifecycle::VenueConfirmed {
-                responses: responses.clone(),
-                hangout,
-            };
+    pub fn can_retry(ping: &Ping, user_id: Uuid) -> Result<(), AppError> {
+        if ping.initiator != user_id {
+            return Err(AppError::Forbidden(
+                "Only initiator can retry ping".to_string(),
+            ));
         }
+
+        if !ping.lifecycle.can_retry() {
+            return Err(AppError::Conflict(format!(
+                "Cannot retry when ping is in {} state",
+                ping.lifecycle.state_name()
+            )));
+        }
+
+        Ok(())
     }
 
-    pub fn transition_to_active(ping: &mut Ping) {
-        if let PingLifecycle::VenueConfirmed { responses, hangout } = &ping.lifecycle {
-            ping.lifecycle = PingLifecycle::ActiveHangout {
-                responses: responses.clone(),
-                hangout: hangout.clone(),
-            };
+    /// Move the ping along the lifecycle table on behalf of `actor`, leaving
+    /// it untouched if `event` isn't allowed from its current state.
+    pub fn apply(ping: &mut Ping, event: PingEvent, actor: Actor) -> Result<(), TransitionError> {
+        ping.lifecycle = transitions::apply(&ping.lifecycle, event, actor, Utc::now())?;
+        Ok(())
+    }
+
+    /// Record a response; a yes beyond the ping's capacity joins the waitlist.
+    pub fn add_response(ping: &mut Ping, response: Response) -> Result<(), TransitionError> {
+        let waitlisted = response.answer && ping.is_full();
+        let user = response.user;
+        Self::apply(
+            ping,
+            PingEvent::ResponseReceived(response),
+            Actor::User { user_id: user },
+        )?;
+        if waitlisted {
+            ping.waitlist.push(user);
+        }
+        Ok(())
+    }
+
+    /// Close a gathering targeted ping as no match once every invitee has
+    /// said no. Whole-group pings wait for the initiator to trigger matching.
+    pub fn decline_if_everyone_said_no(
+        ping: &mut Ping,
+        audience: &[Uuid],
+        user_id: Uuid,
+    ) -> Result<(), TransitionError> {
+        let everyone_said_no = matches!(ping.lifecycle, PingLifecycle::Gathering { .. })
+            && !ping.invitees.is_empty()
+            && ping.all_responded(audience)
+            && ping.responses().iter().all(|r| !r.answer);
+        if everyone_said_no {
+            Self::apply(ping, PingEvent::AllDeclined, Actor::User { user_id })?;
         }
+        Ok(())
+    }
+
+    pub fn transition_to_matching(
+        ping: &mut Ping,
+        user_id: Uuid,
+        match_results: MatchResults,
+    ) -> Result<(), TransitionError> {
+        let event = if match_results.has_match {
+            PingEvent::MatchFound(match_results)
+        } else if !ping.responses().is_empty() && ping.positive_responses().is_empty() {
+            PingEvent::AllDeclined
+        } else {
+            PingEvent::NoMatchFound
+        };
+        Self::apply(ping, event, Actor::User { user_id })
+    }
+
+    pub fn transition_to_venue_confirmed(
+        ping: &mut Ping,
+        user_id: Uuid,
+        hangout: HangoutData,
+    ) -> Result<(), TransitionError> {
+        Self::apply(
+            ping,
+            PingEvent::VenueChosen(hangout),
+            Actor::User { user_id },
+        )
     }
 
-    pub fn transition_to_complete(ping: &mut Ping) {
-        if let PingLifecycle::ActiveHangout { responses, hangout } = &ping.lifecycle {
-            ping.lifecycle = PingLifecycle::Complete {
-                responses: responses.clone(),
-                hangout: hangout.clone(),
+    /// Swap the confirmed venue for a fallback, keeping attendees and timeline.
+    pub fn transition_to_fallback_venue(
+        ping: &mut Ping,
+        user_id: Uuid,
+        remaining: MatchResults,
+        venue: Venue,
+    ) -> Result<(), TransitionError> {
+        Self::apply(
+            ping,
+            PingEvent::FallbackVenueChosen { remaining, venue },
+            Actor::User { user_id },
+        )
+    }
+
+    /// Send the group back to voting on the remaining venue options.
+    pub fn transition_to_revote(
+        ping: &mut Ping,
+        user_id: Uuid,
+        remaining: MatchResults,
+    ) -> Result<(), TransitionError> {
+        Self::apply(
+            ping,
+            PingEvent::VenueRevote(remaining),
+            Actor::User { user_id },
+        )
+    }
+
+    /// Drop an attendee from a confirmed hangout and record them as a no.
+    /// The ping is cancelled once nobody is left attending.
+    pub fn transition_to_withdrawn(ping: &mut Ping, user_id: Uuid) -> Result<(), TransitionError> {
+        let PingLifecycle::VenueConfirmed {
+            responses, hangout, ..
+        } = &mut ping.lifecycle
+        else {
+            return Err(TransitionError {
+                from: ping.lifecycle.state(),
+                event: PingEventKind::AllCancelled,
+            });
+        };
+
+        hangout.remove_attendee(user_id);
+        if let Some(response) = responses.iter_mut().find(|r| r.user == user_id) {
+            response.decline();
+        }
+
+        Self::promote_waitlisted(ping);
+        if ping
+            .lifecycle
+            .hangout()
+            .is_some_and(|h| h.confirmed_attendees.is_empty())
+        {
+            Self::apply(ping, PingEvent::AllCancelled, Actor::User { user_id })?;
+        }
+        Ok(())
+    }
+
+    /// Fill open spots from the front of the waitlist: as yes responses
+    /// while gathering, as attendees once the hangout is confirmed.
+    pub fn promote_waitlisted(ping: &mut Ping) {
+        while let Some(&next) = ping.waitlist.first() {
+            let has_room = match &ping.lifecycle {
+                PingLifecycle::PingSent { .. } | PingLifecycle::Gathering { .. } => !ping.is_full(),
+                PingLifecycle::VenueConfirmed { hangout, .. }
+                | PingLifecycle::ActiveHangout { hangout, .. } => !hangout.is_full(),
+                _ => false,
             };
+            if !has_room {
+                break;
+            }
+
+            ping.waitlist.remove(0);
+            Self::transition_to_joined(ping, next);
+        }
+    }
+
+    /// Late join: straight in while there's room, otherwise queued for the
+    /// initiator to decide.
+    pub fn transition_to_join_requested(ping: &mut Ping, user_id: Uuid) {
+        let full = ping.lifecycle.hangout().is_some_and(|h| h.is_full());
+        if full {
+            if let Some(hangout) = ping.lifecycle.hangout_mut() {
+                hangout.request_join(user_id);
+            }
+        } else {
+            Self::transition_to_joined(ping, user_id);
         }
     }
 
-    pub fn transition_to_cancelled(ping: &mut Ping) {
-        let responses = ping.lifecycle.responses().to_vec();
-        ping.lifecycle = PingLifecycle::Cancelled { responses };
+    /// Add a late joiner to the hangout, counting them as a yes.
+    pub fn transition_to_joined(ping: &mut Ping, user_id: Uuid) {
+        if let PingLifecycle::VenueConfirmed {
+            responses, hangout, ..
+        }
+        | PingLifecycle::ActiveHangout {
+            responses, hangout, ..
+        } = &mut ping.lifecycle
+        {
+            hangout.remove_join_request(user_id);
+            hangout.add_attendee(user_id);
+            if let Some(response) = responses.iter_mut().find(|r| r.user == user_id) {
+                response.accept();
+            }
+        }
+    }
+
+    pub fn transition_to_active(ping: &mut Ping, user_id: Uuid) -> Result<(), TransitionError> {
+        Self::apply(ping, PingEvent::InitiatorStarts, Actor::User { user_id })
+    }
+
+    pub fn transition_to_complete(ping: &mut Ping, user_id: Uuid) -> Result<(), TransitionError> {
+        Self::apply(ping, PingEvent::InitiatorEnds, Actor::User { user_id })
+    }
+
+    /// Complete an active hangout once the last attendee has left, crediting
+    /// `user_id` whose update emptied it. Returns whether it was completed.
+    pub fn complete_if_everyone_left(ping: &mut Ping, user_id: Uuid) -> bool {
+        match &ping.lifecycle {
+            PingLifecycle::ActiveHangout { hangout, .. } if hangout.everyone_left() => {
+                Self::apply(ping, PingEvent::AllLeft, Actor::User { user_id }).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn transition_to_cancelled(
+        ping: &mut Ping,
+        user_id: Uuid,
+        reason: Option<String>,
+    ) -> Result<(), TransitionError> {
+        Self::apply(
+            ping,
+            PingEvent::InitiatorCancels { reason },
+            Actor::User { user_id },
+        )
+    }
+
+    /// Move an attendee along the attendee transition table, completing the
+    /// hangout if that was the last one to leave. Returns whether the ping
+    /// was completed.
+    pub fn transition_attendee(
+        ping: &mut Ping,
+        user_id: Uuid,
+        change: StatusChange,
+    ) -> Result<bool, AppError> {
+        if ping.lifecycle.is_terminal() {
+            return Err(AppError::Conflict(format!(
+                "Attendee statuses can't change once the ping is {}",
+                ping.lifecycle.state()
+            )));
+        }
+        let hangout = ping.lifecycle.hangout_mut().ok_or_else(|| {
+            AppError::Conflict("Ping does not have an active hangout".to_string())
+        })?;
+        let current = hangout
+            .attendee_status(user_id)
+            .ok_or_else(|| AppError::NotFound("Attendee".to_string()))?;
+        if !transitions::attendee_allows(current, change.status) {
+            return Err(AppError::Conflict(format!(
+                "Cannot change attendee status from {current} to {}",
+                change.status
+            )));
+        }
+
+        hangout.update_attendee_status(user_id, change);
+        Ok(Self::complete_if_everyone_left(ping, user_id))
     }
 
-    pub fn create_hangout_data(ping: &Ping, timeline: Timeline) -> HangoutData {
+    pub fn create_hangout_data(
+        ping: &Ping,
+        timeline: Timeline,
+        venue: Option<Venue>,
+    ) -> HangoutData {
         let attendees: Vec<Uuid> = ping.positive_responses().iter().map(|r| r.user).collect();
-        HangoutData::new(attendees, timeline)
+        let mut hangout = HangoutData::new(attendees, timeline, venue);
+        hangout.capacity = ping.capacity;
+        hangout
     }
 }
@@ -0,0 +1,715 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::{
+    Actor, AppError, AttendeeStatus, HangoutData, LifecycleState, MatchResults, PingLifecycle,
+    Response, Venue,
+};
+
+/// Kinds of things that can happen to a ping, named after SPEC Appendix B
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PingEventKind {
+    SendTimeReached,
+    ResponseReceived,
+    MatchFound,
+    NoMatchFound,
+    AllDeclined,
+    VenueChosen,
+    FallbackVenueChosen,
+    VenueRevote,
+    StartTimeReached,
+    InitiatorStarts,
+    AllCancelled,
+    InitiatorCancels,
+    AllLeft,
+    TimedOut,
+    InitiatorEnds,
+}
+
+impl PingEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PingEventKind::SendTimeReached => "send_time_reached",
+            PingEventKind::ResponseReceived => "response_received",
+            PingEventKind::MatchFound => "match_found",
+            PingEventKind::NoMatchFound => "no_match_found",
+            PingEventKind::AllDeclined => "all_declined",
+            PingEventKind::VenueChosen => "venue_chosen",
+            PingEventKind::FallbackVenueChosen => "fallback_venue_chosen",
+            PingEventKind::VenueRevote => "venue_revote",
+            PingEventKind::StartTimeReached => "start_time_reached",
+            PingEventKind::InitiatorStarts => "initiator_starts",
+            PingEventKind::AllCancelled => "all_cancelled",
+            PingEventKind::InitiatorCancels => "initiator_cancels",
+            PingEventKind::AllLeft => "all_left",
+            PingEventKind::TimedOut => "timed_out",
+            PingEventKind::InitiatorEnds => "initiator_ends",
+        }
+    }
+}
+
+impl fmt::Display for PingEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An event along with whatever the next state needs to be built.
+#[derive(Debug, Clone)]
+pub enum PingEvent {
+    SendTimeReached,
+    ResponseReceived(Response),
+    MatchFound(MatchResults),
+    NoMatchFound,
+    AllDeclined,
+    VenueChosen(HangoutData),
+    FallbackVenueChosen {
+        remaining: MatchResults,
+        venue: Venue,
+    },
+    VenueRevote(MatchResults),
+    StartTimeReached,
+    InitiatorStarts,
+    AllCancelled,
+    InitiatorCancels {
+        reason: Option<String>,
+    },
+    AllLeft,
+    TimedOut,
+    InitiatorEnds,
+}
+
+impl PingEvent {
+    pub fn kind(&self) -> PingEventKind {
+        match self {
+            PingEvent::SendTimeReached => PingEventKind::SendTimeReached,
+            PingEvent::ResponseReceived(_) => PingEventKind::ResponseReceived,
+            PingEvent::MatchFound(_) => PingEventKind::MatchFound,
+            PingEvent::NoMatchFound => PingEventKind::NoMatchFound,
+            PingEvent::AllDeclined => PingEventKind::AllDeclined,
+            PingEvent::VenueChosen(_) => PingEventKind::VenueChosen,
+            PingEvent::FallbackVenueChosen { .. } => PingEventKind::FallbackVenueChosen,
+            PingEvent::VenueRevote(_) => PingEventKind::VenueRevote,
+            PingEvent::StartTimeReached => PingEventKind::StartTimeReached,
+            PingEvent::InitiatorStarts => PingEventKind::InitiatorStarts,
+            PingEvent::AllCancelled => PingEventKind::AllCancelled,
+            PingEvent::InitiatorCancels { .. } => PingEventKind::InitiatorCancels,
+            PingEvent::AllLeft => PingEventKind::AllLeft,
+            PingEvent::TimedOut => PingEventKind::TimedOut,
+            PingEvent::InitiatorEnds => PingEventKind::InitiatorEnds,
+        }
+    }
+}
+
+/// One allowed move in the ping lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Transition {
+    pub from: LifecycleState,
+    pub event: PingEventKind,
+    pub to: LifecycleState,
+    pub description: &'static str,
+}
+
+const fn transition(
+    from: LifecycleState,
+    event: PingEventKind,
+    to: LifecycleState,
+    description: &'static str,
+) -> Transition {
+    Transition {
+        from,
+        event,
+        to,
+        description,
+    }
+}
+
+/// Every allowed transition. Anything not listed here is rejected.
+pub const TRANSITIONS: &[Transition] = {
+    use LifecycleState::*;
+    use PingEventKind::*;
+
+    &[
+        transition(Scheduled, SendTimeReached, PingSent, "Send time reached"),
+        transition(Scheduled, InitiatorCancels, Cancelled, "Initiator cancels"),
+        transition(
+            PingSent,
+            ResponseReceived,
+            Gathering,
+            "First response received",
+        ),
+        transition(PingSent, InitiatorCancels, Cancelled, "Initiator cancels"),
+        transition(Gathering, ResponseReceived, Gathering, "Response received"),
+        transition(
+            Gathering,
+            MatchFound,
+            Matching,
+            "Matching found a time; venue vote opens",
+        ),
+        transition(Gathering, NoMatchFound, NoMatch, "No matches"),
+        transition(Gathering, AllDeclined, NoMatch, "All respond no"),
+        transition(Gathering, InitiatorCancels, Cancelled, "Initiator cancels"),
+        transition(Matching, VenueChosen, VenueConfirmed, "Voting complete"),
+        transition(Matching, InitiatorCancels, Cancelled, "Initiator cancels"),
+        transition(
+            VenueConfirmed,
+            StartTimeReached,
+            ActiveHangout,
+            "Start time reached",
+        ),
+        transition(
+            VenueConfirmed,
+            InitiatorStarts,
+            ActiveHangout,
+            "Initiator starts early",
+        ),
+        transition(
+            VenueConfirmed,
+            FallbackVenueChosen,
+            VenueConfirmed,
+            "Venue unavailable; runner-up chosen",
+        ),
+        transition(
+            VenueConfirmed,
+            VenueRevote,
+            Matching,
+            "Venue unavailable; group re-votes",
+        ),
+        transition(VenueConfirmed, AllCancelled, Cancelled, "All cancel"),
+        transition(
+            VenueConfirmed,
+            InitiatorCancels,
+            Cancelled,
+            "Initiator cancels",
+        ),
+        transition(ActiveHangout, AllLeft, Complete, "All left"),
+        transition(ActiveHangout, TimedOut, Complete, "Timeout"),
+        transition(ActiveHangout, InitiatorEnds, Complete, "Initiator ends"),
+        transition(
+            ActiveHangout,
+            InitiatorCancels,
+            Cancelled,
+            "Initiator cancels",
+        ),
+    ]
+};
+
+pub fn find(from: LifecycleState, event: PingEventKind) -> Option<&'static Transition> {
+    TRANSITIONS
+        .iter()
+        .find(|t| t.from == from && t.event == event)
+}
+
+pub fn allows(from: LifecycleState, event: PingEventKind) -> bool {
+    find(from, event).is_some()
+}
+
+/// One allowed change of an attendee's status during a hangout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AttendeeTransition {
+    pub from: AttendeeStatus,
+    pub to: AttendeeStatus,
+    pub description: &'static str,
+}
+
+const fn attendee_transition(
+    from: AttendeeStatus,
+    to: AttendeeStatus,
+    description: &'static str,
+) -> AttendeeTransition {
+    AttendeeTransition {
+        from,
+        to,
+        description,
+    }
+}
+
+/// The attendee sub-state machine: Pending → Enroute/Delayed → Arrived →
+/// Left, and back out again for anyone who rejoins. Nothing returns to
+/// Pending.
+pub const ATTENDEE_TRANSITIONS: &[AttendeeTransition] = {
+    use AttendeeStatus::*;
+
+    &[
+        attendee_transition(Pending, Enroute, "Heading over"),
+        attendee_transition(Pending, Delayed, "Running late"),
+        attendee_transition(Pending, Arrived, "Arrived"),
+        attendee_transition(Pending, Left, "Not coming after all"),
+        attendee_transition(Enroute, Enroute, "ETA updated"),
+        attendee_transition(Enroute, Delayed, "Running late"),
+        attendee_transition(Enroute, Arrived, "Arrived"),
+        attendee_transition(Enroute, Left, "Turned back"),
+        attendee_transition(Delayed, Delayed, "ETA updated"),
+        attendee_transition(Delayed, Enroute, "Back on track"),
+        attendee_transition(Delayed, Arrived, "Arrived"),
+        attendee_transition(Delayed, Left, "Not coming after all"),
+        attendee_transition(Arrived, Left, "Left"),
+        attendee_transition(Left, Enroute, "Rejoining"),
+        attendee_transition(Left, Arrived, "Came back"),
+    ]
+};
+
+pub fn attendee_allows(from: AttendeeStatus, to: AttendeeStatus) -> bool {
+    ATTENDEE_TRANSITIONS
+        .iter()
+        .any(|t| t.from == from && t.to == to)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Cannot apply {event} when ping is in {from} state")]
+pub struct TransitionError {
+    pub from: LifecycleState,
+    pub event: PingEventKind,
+}
+
+impl From<TransitionError> for AppError {
+    fn from(err: TransitionError) -> Self {
+        AppError::Conflict(err.to_string())
+    }
+}
+
+/// Reason recorded when the last attendee withdraws from a hangout
+pub const ALL_CANCELLED_REASON: &str = "Everyone withdrew";
+
+/// Work out the lifecycle that `event` leads to, or why it isn't allowed.
+/// The new state is stamped with `at` and `actor`; staying in the same state
+/// keeps the original stamp so phase durations stay meaningful.
+pub fn apply(
+    lifecycle: &PingLifecycle,
+    event: PingEvent,
+    actor: Actor,
+    at: DateTime<Utc>,
+) -> Result<PingLifecycle, TransitionError> {
+    let error = TransitionError {
+        from: lifecycle.state(),
+        event: event.kind(),
+    };
+    let Some(transition) = find(error.from, error.event) else {
+        return Err(error);
+    };
+    let (entered_at, actor) = if transition.to == transition.from {
+        (lifecycle.entered_at(), lifecycle.actor())
+    } else {
+        (at, actor)
+    };
+
+    let mut responses = lifecycle.responses().to_vec();
+    let next = match (lifecycle, event) {
+        (_, PingEvent::SendTimeReached) => PingLifecycle::PingSent { entered_at, actor },
+        (_, PingEvent::ResponseReceived(response)) => {
+            responses.push(response);
+            PingLifecycle::Gathering {
+                entered_at,
+                actor,
+                responses,
+            }
+        }
+        (_, PingEvent::MatchFound(match_results)) => PingLifecycle::Matching {
+            entered_at,
+            actor,
+            responses,
+            match_results,
+            pending_hangout: None,
+        },
+        (_, PingEvent::NoMatchFound | PingEvent::AllDeclined) => PingLifecycle::NoMatch {
+            entered_at,
+            actor,
+            responses,
+        },
+        (PingLifecycle::Matching { match_results, .. }, PingEvent::VenueChosen(hangout)) => {
+            PingLifecycle::VenueConfirmed {
+                entered_at,
+                actor,
+                responses,
+                match_results: match_results.clone(),
+                hangout,
+            }
+        }
+        (
+            PingLifecycle::VenueConfirmed { hangout, .. },
+            PingEvent::FallbackVenueChosen { remaining, venue },
+        ) => PingLifecycle::VenueConfirmed {
+            entered_at,
+            actor,
+            responses,
+            match_results: remaining,
+            hangout: HangoutData {
+                venue: Some(venue),
+                ..hangout.clone()
+            },
+        },
+        // The hangout is held over so attendees and timeline survive the re-vote
+        (PingLifecycle::VenueConfirmed { hangout, .. }, PingEvent::VenueRevote(remaining)) => {
+            PingLifecycle::Matching {
+                entered_at,
+                actor,
+                responses,
+                match_results: remaining,
+                pending_hangout: Some(HangoutData {
+                    venue: None,
+                    ..hangout.clone()
+                }),
+            }
+        }
+        (
+            PingLifecycle::VenueConfirmed { hangout, .. },
+            PingEvent::StartTimeReached | PingEvent::InitiatorStarts,
+        ) => PingLifecycle::ActiveHangout {
+            entered_at,
+            actor,
+            responses,
+            hangout: hangout.clone(),
+        },
+        (
+            PingLifecycle::ActiveHangout { hangout, .. },
+            PingEvent::AllLeft | PingEvent::TimedOut | PingEvent::InitiatorEnds,
+        ) => PingLifecycle::Complete {
+            entered_at,
+            actor,
+            responses,
+            hangout: hangout.clone(),
+        },
+        (_, PingEvent::AllCancelled) => PingLifecycle::Cancelled {
+            entered_at,
+            actor,
+            responses,
+            reason: Some(ALL_CANCELLED_REASON.to_string()),
+        },
+        (_, PingEvent::InitiatorCancels { reason }) => PingLifecycle::Cancelled {
+            entered_at,
+            actor,
+            responses,
+            reason,
+        },
+        _ => return Err(error),
+    };
+
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateResponseRequest, Timeline};
+    use LifecycleState::*;
+    use PingEventKind::*;
+    use uuid::Uuid;
+
+    /// SPEC Appendix B, with "Venue Vote" being our `Matching` state and the
+    /// "Matching" step folded into `trigger_match` from `Gathering`. Venue
+    /// Vote's "All cancel" is left out: nobody can withdraw during the vote,
+    /// so only the initiator cancels from there.
+    const APPENDIX_B: &[(LifecycleState, PingEventKind, LifecycleState)] = &[
+        (PingSent, ResponseReceived, Gathering),
+        (PingSent, InitiatorCancels, Cancelled),
+        (Gathering, ResponseReceived, Gathering),
+        (Gathering, MatchFound, Matching),
+        (Gathering, AllDeclined, NoMatch),
+        (Gathering, InitiatorCancels, Cancelled),
+        (Gathering, NoMatchFound, NoMatch),
+        (Matching, VenueChosen, VenueConfirmed),
+        (VenueConfirmed, StartTimeReached, ActiveHangout),
+        (VenueConfirmed, AllCancelled, Cancelled),
+        (ActiveHangout, AllLeft, Complete),
+        (ActiveHangout, TimedOut, Complete),
+        (ActiveHangout, InitiatorEnds, Complete),
+    ];
+
+    /// Transitions beyond Appendix B that the API supports
+    const EXTENSIONS: &[(LifecycleState, PingEventKind, LifecycleState)] = &[
+        (Scheduled, SendTimeReached, PingSent),
+        (Scheduled, InitiatorCancels, Cancelled),
+        (Matching, InitiatorCancels, Cancelled),
+        (VenueConfirmed, InitiatorStarts, ActiveHangout),
+        (VenueConfirmed, FallbackVenueChosen, VenueConfirmed),
+        (VenueConfirmed, VenueRevote, Matching),
+        (VenueConfirmed, InitiatorCancels, Cancelled),
+        (ActiveHangout, InitiatorCancels, Cancelled),
+    ];
+
+    const ALL_EVENTS: [PingEventKind; 15] = [
+        SendTimeReached,
+        ResponseReceived,
+        MatchFound,
+        NoMatchFound,
+        AllDeclined,
+        VenueChosen,
+        FallbackVenueChosen,
+        VenueRevote,
+        StartTimeReached,
+        InitiatorStarts,
+        AllCancelled,
+        InitiatorCancels,
+        AllLeft,
+        TimedOut,
+        InitiatorEnds,
+    ];
+
+    fn match_results() -> MatchResults {
+        MatchResults {
+            ping_id: Uuid::new_v4(),
+            overlap: None,
+            has_match: true,
+            venue_options: vec![],
+        }
+    }
+
+    fn hangout() -> HangoutData {
+        let start = Utc::now();
+        let timeline = Timeline {
+            start,
+            end: start + chrono::Duration::hours(2),
+        };
+        HangoutData::new(vec![Uuid::new_v4()], timeline, None)
+    }
+
+    fn venue() -> Venue {
+        crate::models::CustomVenue {
+            name: "Corner Bar".to_string(),
+            location: None,
+            area: None,
+        }
+        .into()
+    }
+
+    fn lifecycle(state: LifecycleState) -> PingLifecycle {
+        let entered_at = Utc::now();
+        let actor = Actor::System;
+        let responses = vec![];
+        match state {
+            Scheduled => PingLifecycle::Scheduled {
+                entered_at,
+                actor,
+                send_at: entered_at + chrono::Duration::days(1),
+            },
+            PingSent => PingLifecycle::PingSent { entered_at, actor },
+            Gathering => PingLifecycle::Gathering {
+                entered_at,
+                actor,
+                responses,
+            },
+            Matching => PingLifecycle::Matching {
+                entered_at,
+                actor,
+                responses,
+                match_results: match_results(),
+                pending_hangout: None,
+            },
+            VenueConfirmed => PingLifecycle::VenueConfirmed {
+                entered_at,
+                actor,
+                responses,
+                match_results: match_results(),
+                hangout: hangout(),
+            },
+            ActiveHangout => PingLifecycle::ActiveHangout {
+                entered_at,
+                actor,
+                responses,
+                hangout: hangout(),
+            },
+            Complete => PingLifecycle::Complete {
+                entered_at,
+                actor,
+                responses,
+                hangout: hangout(),
+            },
+            Cancelled => PingLifecycle::Cancelled {
+                entered_at,
+                actor,
+                responses,
+                reason: None,
+            },
+            NoMatch => PingLifecycle::NoMatch {
+                entered_at,
+                actor,
+                responses,
+            },
+        }
+    }
+
+    fn event(kind: PingEventKind) -> PingEvent {
+        match kind {
+            SendTimeReached => PingEvent::SendTimeReached,
+            ResponseReceived => PingEvent::ResponseReceived(Response::new(CreateResponseRequest {
+                user: Uuid::new_v4(),
+                answer: true,
+                availability: None,
+                preferences: None,
+            })),
+            MatchFound => PingEvent::MatchFound(match_results()),
+            NoMatchFound => PingEvent::NoMatchFound,
+            AllDeclined => PingEvent::AllDeclined,
+            VenueChosen => PingEvent::VenueChosen(hangout()),
+            FallbackVenueChosen => PingEvent::FallbackVenueChosen {
+                remaining: match_results(),
+                venue: venue(),
+            },
+            VenueRevote => PingEvent::VenueRevote(match_results()),
+            StartTimeReached => PingEvent::StartTimeReached,
+            InitiatorStarts => PingEvent::InitiatorStarts,
+            AllCancelled => PingEvent::AllCancelled,
+            InitiatorCancels => PingEvent::InitiatorCancels { reason: None },
+            AllLeft => PingEvent::AllLeft,
+            TimedOut => PingEvent::TimedOut,
+            InitiatorEnds => PingEvent::InitiatorEnds,
+        }
+    }
+
+    fn expected(from: LifecycleState, kind: PingEventKind) -> Option<LifecycleState> {
+        APPENDIX_B
+            .iter()
+            .chain(EXTENSIONS)
+            .find(|(f, e, _)| *f == from && *e == kind)
+            .map(|(_, _, to)| *to)
+    }
+
+    /// Drive every state through every event: Appendix B edges and our
+    /// extensions land where listed, and anything else is rejected.
+    #[test]
+    fn test_table_matches_spec() {
+        for (from, kind, _) in EXTENSIONS {
+            assert!(
+                !APPENDIX_B.iter().any(|(f, e, _)| f == from && e == kind),
+                "{from} --{kind}--> overrides Appendix B"
+            );
+        }
+
+        for from in LifecycleState::ALL {
+            for kind in ALL_EVENTS {
+                let result = apply(&lifecycle(from), event(kind), Actor::System, Utc::now());
+                match expected(from, kind) {
+                    Some(to) => assert_eq!(result.map(|l| l.state()), Ok(to), "{from} --{kind}-->"),
+                    None => assert_eq!(
+                        result.map(|l| l.state()),
+                        Err(TransitionError { from, event: kind }),
+                        "{from} --{kind}-->"
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_terminal_states_have_no_way_out() {
+        for transition in TRANSITIONS {
+            assert!(!transition.from.is_terminal(), "{transition:?}");
+        }
+    }
+
+    #[test]
+    fn test_responses_carry_over() {
+        let gathering = apply(
+            &lifecycle(PingSent),
+            event(ResponseReceived),
+            Actor::System,
+            Utc::now(),
+        )
+        .unwrap();
+        let gathering = apply(
+            &gathering,
+            event(ResponseReceived),
+            Actor::System,
+            Utc::now(),
+        )
+        .unwrap();
+        assert_eq!(gathering.responses().len(), 2);
+
+        let cancelled = apply(
+            &gathering,
+            event(InitiatorCancels),
+            Actor::System,
+            Utc::now(),
+        )
+        .unwrap();
+        assert_eq!(cancelled.responses().len(), 2);
+    }
+
+    #[test]
+    fn test_revote_holds_hangout_without_venue() {
+        let mut confirmed = lifecycle(VenueConfirmed);
+        if let PingLifecycle::VenueConfirmed { hangout, .. } = &mut confirmed {
+            hangout.venue = Some(venue());
+        }
+
+        let revote = apply(&confirmed, event(VenueRevote), Actor::System, Utc::now()).unwrap();
+        let pending = revote.pending_hangout().unwrap();
+        assert!(pending.venue.is_none());
+        assert_eq!(pending.confirmed_attendees.len(), 1);
+    }
+
+    #[test]
+    fn test_attendee_transitions() {
+        use AttendeeStatus::*;
+
+        const ALL: [AttendeeStatus; 5] = [Pending, Enroute, Delayed, Arrived, Left];
+        let allowed = |from, to| match (from, to) {
+            (_, Pending) => false,
+            (Pending, _) => true,
+            (Enroute | Delayed, _) => true,
+            (Arrived, to) => to == Left,
+            (Left, to) => matches!(to, Enroute | Arrived),
+        };
+
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    attendee_allows(from, to),
+                    allowed(from, to),
+                    "{from} -> {to}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_transitions_are_stamped() {
+        let user_id = Uuid::new_v4();
+        let at = Utc::now() + chrono::Duration::minutes(5);
+
+        let sent = lifecycle(PingSent);
+        let gathering = apply(&sent, event(ResponseReceived), Actor::User { user_id }, at).unwrap();
+        assert_eq!(gathering.entered_at(), at);
+        assert_eq!(gathering.actor(), Actor::User { user_id });
+
+        // Staying in the same state keeps the original stamp
+        let later = at + chrono::Duration::minutes(5);
+        let still_gathering =
+            apply(&gathering, event(ResponseReceived), Actor::System, later).unwrap();
+        assert_eq!(still_gathering.entered_at(), at);
+        assert_eq!(still_gathering.actor(), Actor::User { user_id });
+    }
+
+    #[test]
+    fn test_cancellation_reason() {
+        let cancelled = apply(
+            &lifecycle(Gathering),
+            PingEvent::InitiatorCancels {
+                reason: Some("Rain".to_string()),
+            },
+            Actor::System,
+            Utc::now(),
+        )
+        .unwrap();
+        assert!(matches!(
+            cancelled,
+            PingLifecycle::Cancelled { reason: Some(ref r), .. } if r == "Rain"
+        ));
+
+        let withdrawn = apply(
+            &lifecycle(VenueConfirmed),
+            event(AllCancelled),
+            Actor::System,
+            Utc::now(),
+        )
+        .unwrap();
+        assert!(matches!(
+            withdrawn,
+            PingLifecycle::Cancelled { reason: Some(ref r), .. } if r == ALL_CANCELLED_REASON
+        ));
+    }
+}